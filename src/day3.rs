@@ -1,32 +1,570 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
-use regex::Regex;
 use std::str::FromStr;
+use std::time::Instant;
 
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let input = fs::read_to_string("input/day3.txt")?;
-    let lines: Vec<_> = input.lines().collect();
+    let args: Vec<String> = std::env::args().collect();
+    let arg = |flag: &str| args.iter().skip_while(|arg| *arg != flag).nth(1);
+    let mut format = Format { pad: args.iter().any(|arg| arg == "--pad"), ..Format::default() };
+    if let Some(empty) = arg("--empty") {
+        let mut chars = empty.chars();
+        format.empty = match (chars.next(), chars.next()) {
+            (Some(chr), None) => chr,
+            _ => Err(format!("--empty takes a single character, not '{}'", empty))?,
+        };
+    }
 
-    let (numbers_boxes, symbols_boxes) = get_parts_and_symbols(lines.clone());
-    let symboled_numbers: Vec<_> = numbers_boxes.iter().filter(|numbox| get_symbol_neighbours(numbox, &symbols_boxes).len() > 0).collect();
-    let machine_part_sum: u32 = symboled_numbers.iter().map(|numbox| numbox.value).sum();
-    println!("{}", machine_part_sum);
+    let mut rule = GearRule::default();
+    if let Some(symbols) = arg("--gears") {
+        rule.symbols = symbols.chars().collect();
+    }
+    if let Some(count) = arg("--gear-count") {
+        rule.count = count.parse()?;
+    }
+    if let Some(aggregate) = arg("--ratio") {
+        rule.aggregate = aggregate.parse()?;
+    }
+
+    let mut adjacency = Adjacency { wrap: args.iter().any(|arg| arg == "--wrap"), ..Adjacency::default() };
+    if let Some(neighbourhood) = arg("--neighbourhood") {
+        adjacency.neighbourhood = neighbourhood.parse()?;
+    }
 
-    let gears = symbols_boxes.iter()
-        .filter(|s| s.chr == '*')
-        .filter(|s| get_number_neighbours(s, &symboled_numbers).len() == 2);
+    if let Some(style) = arg("--render") {
+        let input = fs::read_to_string("input/day3.txt")?;
+        let legend = args.iter().any(|arg| arg == "--legend");
+        print!("{}", render(input.lines().collect(), &format, &rule, &adjacency, &style.parse()?, legend)?);
+        return Ok(());
+    }
 
-    let ratios: Vec<Vec<_>> = gears.map(|g| get_number_neighbours(&g, &symboled_numbers).iter().map(|n| n.value).collect())
-        .collect();
+    // edits read from stdin as "line column character", counting from one, or
+    // "show" to print the schematic as it stands
+    if args.iter().any(|arg| arg == "--interactive") {
+        let input = fs::read_to_string("input/day3.txt")?;
+        let mut engine = Engine::new(input.lines().collect(), &format, &rule, &adjacency)?;
+        println!("{:?}", engine.sums());
+        for edit in std::io::stdin().lines() {
+            let edit = edit?;
+            if edit.trim() == "show" {
+                engine.lines().iter().for_each(|line| println!("{}", line));
+                continue;
+            }
+            let fields: Vec<_> = edit.split_whitespace().collect();
+            let [line, column, chr] = fields[..] else {
+                eprintln!("expected \"line column character\", got '{}'", edit);
+                continue;
+            };
+            let (Ok(line @ 1..), Ok(column @ 1..), Ok(chr)) = (line.parse::<usize>(), column.parse::<usize>(), chr.parse::<char>()) else {
+                eprintln!("expected \"line column character\", got '{}'", edit);
+                continue;
+            };
+            match engine.set(column - 1, line - 1, chr) {
+                Ok(()) => println!("{:?}", engine.sums()),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        return Ok(());
+    }
 
-    let ratio_sum: u32 = ratios.iter().map(|v| { let p: u32 = v.iter().product(); p }).sum();
+    if args.iter().any(|arg| arg == "--bench") {
+        let schematic = generate_schematic(2000, 2000);
+        let start = Instant::now();
+        let (machine_part_sum, ratio_sum) = solve(schematic.lines().collect(), &format, &rule, &adjacency)?;
+        println!("2000x2000: {} {} in {:?}", machine_part_sum, ratio_sum, start.elapsed());
+        return Ok(());
+    }
 
+    let input = fs::read_to_string("input/day3.txt")?;
+    let (machine_part_sum, ratio_sum) = solve(input.lines().collect(), &format, &rule, &adjacency)?;
+    println!("{}", machine_part_sum);
     println!("{:?}", ratio_sum);
 
     Ok(())
 }
 
+fn solve(lines: Vec<&str>, format: &Format, rule: &GearRule, adjacency: &Adjacency) -> Result<(u64, u64), String> {
+    let (width, height) = size(&lines);
+    let neighbours = adjacency.on(width, height);
+    let (numbers_boxes, symbols_boxes) = get_parts_and_symbols(lines, format)?;
+    let symbols = index_symbols(&symbols_boxes);
+    let symboled_numbers: Vec<_> = numbers_boxes.iter().filter(|numbox| !get_symbol_neighbours(numbox, &symbols, &neighbours).is_empty()).collect();
+    let machine_part_sum: u64 = symboled_numbers.iter().map(|numbox| numbox.value as u64).sum();
+
+    let numbers = index_numbers(&symboled_numbers);
+    let ratio_sum = symbols_boxes.iter().try_fold(0u64, |sum, symbol| {
+        match rule.ratio(symbol, &numbers, &neighbours)? {
+            Some(ratio) => sum.checked_add(ratio)
+                .ok_or_else(|| format!("line {}, column {}: gear ratio sum too large", symbol.y + 1, symbol.x + 1)),
+            None => Ok(sum),
+        }
+    })?;
+    Ok((machine_part_sum, ratio_sum))
+}
+
+// the width and height of a schematic, short lines padded
+fn size(lines: &[&str]) -> (usize, usize) {
+    (lines.iter().map(|line| line.chars().count()).max().unwrap_or(0), lines.len())
+}
+
+// how many part numbers a gear must touch
+#[derive(Clone, Debug, PartialEq)]
+enum Count {
+    Exact(usize),
+    AtLeast(usize),
+    Range(usize, usize),
+}
+
+impl Count {
+    fn admits(&self, n: usize) -> bool {
+        match *self {
+            Count::Exact(count) => n == count,
+            Count::AtLeast(count) => n >= count,
+            Count::Range(low, high) => (low..=high).contains(&n),
+        }
+    }
+}
+
+impl FromStr for Count {
+    type Err = String;
+
+    // "2", "2+" or "2-4"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |n: &str| n.parse::<usize>().map_err(|e| format!("invalid count '{}' in '{}': {}", n, s, e));
+        if let Some(low) = s.strip_suffix('+') {
+            Ok(Count::AtLeast(number(low)?))
+        } else if let Some((low, high)) = s.split_once('-') {
+            match (number(low)?, number(high)?) {
+                (low, high) if low <= high => Ok(Count::Range(low, high)),
+                _ => Err(format!("empty count range '{}'", s)),
+            }
+        } else {
+            Ok(Count::Exact(number(s)?))
+        }
+    }
+}
+
+// how a gear's part numbers combine into its ratio
+#[derive(Clone, Debug, PartialEq)]
+enum Aggregate {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Aggregate {
+    // None when the ratio does not fit; a gear touching no numbers has a ratio of 0
+    fn apply(&self, values: &[u64]) -> Option<u64> {
+        if values.is_empty() {
+            return Some(0);
+        }
+        match self {
+            Aggregate::Product => values.iter().try_fold(1u64, |acc, v| acc.checked_mul(*v)),
+            Aggregate::Sum => values.iter().try_fold(0u64, |acc, v| acc.checked_add(*v)),
+            Aggregate::Max => values.iter().copied().max(),
+        }
+    }
+}
+
+impl FromStr for Aggregate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "product" => Ok(Aggregate::Product),
+            "sum" => Ok(Aggregate::Sum),
+            "max" => Ok(Aggregate::Max),
+            _ => Err(format!("unknown aggregate '{}', expected product, sum or max", s)),
+        }
+    }
+}
+
+// which symbols are gears, and what their ratio is; the puzzle's gears are
+// '*' touching exactly two part numbers, whose product is the ratio
+#[derive(Clone, Debug, PartialEq)]
+struct GearRule {
+    symbols: Vec<char>,
+    count: Count,
+    aggregate: Aggregate,
+}
+
+impl Default for GearRule {
+    fn default() -> Self {
+        GearRule { symbols: vec!['*'], count: Count::Exact(2), aggregate: Aggregate::Product }
+    }
+}
+
+impl GearRule {
+    // the ratio of the symbol if it is a gear
+    fn ratio(&self, symbol: &Point, numbers: &NumberIndex, neighbours: &Neighbours) -> Result<Option<u64>, String> {
+        if !self.symbols.contains(&symbol.chr) {
+            return Ok(None);
+        }
+        let values: Vec<u64> = get_number_neighbours(symbol, numbers, neighbours).iter().map(|n| n.value as u64).collect();
+        if !self.count.admits(values.len()) {
+            return Ok(None);
+        }
+        self.aggregate.apply(&values).map(Some)
+            .ok_or_else(|| format!("line {}, column {}: gear ratio too large", symbol.y + 1, symbol.x + 1))
+    }
+}
+
+// what a cell of the schematic turns out to be, for rendering
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Class {
+    Empty,
+    Part,
+    Number,
+    Symbol,
+    Gear,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Style {
+    Ansi,
+    Html,
+}
+
+impl Style {
+    fn open(&self, class: Class) -> &'static str {
+        match (self, class) {
+            (_, Class::Empty) => "",
+            (Style::Ansi, Class::Part) => "\x1b[32m",
+            (Style::Ansi, Class::Number) => "\x1b[31m",
+            (Style::Ansi, Class::Symbol) => "\x1b[33m",
+            (Style::Ansi, Class::Gear) => "\x1b[1;35m",
+            (Style::Html, Class::Part) => "<span style=\"color:green\">",
+            (Style::Html, Class::Number) => "<span style=\"color:red\">",
+            (Style::Html, Class::Symbol) => "<span style=\"color:olive\">",
+            (Style::Html, Class::Gear) => "<span style=\"color:purple;font-weight:bold\">",
+        }
+    }
+
+    fn close(&self, class: Class) -> &'static str {
+        match (self, class) {
+            (_, Class::Empty) => "",
+            (Style::Ansi, _) => "\x1b[0m",
+            (Style::Html, _) => "</span>",
+        }
+    }
+
+    fn escape(&self, chr: char) -> String {
+        match (self, chr) {
+            (Style::Html, '<') => "&lt;".to_string(),
+            (Style::Html, '>') => "&gt;".to_string(),
+            (Style::Html, '&') => "&amp;".to_string(),
+            _ => chr.to_string(),
+        }
+    }
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi" => Ok(Style::Ansi),
+            "html" => Ok(Style::Html),
+            _ => Err(format!("unknown style '{}', expected ansi or html", s)),
+        }
+    }
+}
+
+// the schematic with part numbers, other numbers, symbols and gears told apart,
+// and optionally a legend giving each gear's numbers and ratio
+fn render(lines: Vec<&str>, format: &Format, rule: &GearRule, adjacency: &Adjacency, style: &Style, legend: bool) -> Result<String, String> {
+    let (width, height) = size(&lines);
+    let neighbours = adjacency.on(width, height);
+    let (numbers_boxes, symbols_boxes) = get_parts_and_symbols(lines.clone(), format)?;
+    let symbols = index_symbols(&symbols_boxes);
+    let (parts, others): (Vec<_>, Vec<_>) = numbers_boxes.iter().partition(|numbox| !get_symbol_neighbours(numbox, &symbols, &neighbours).is_empty());
+    let numbers = index_numbers(&parts);
+
+    let mut classes: HashMap<(usize, usize), Class> = HashMap::new();
+    classes.extend(parts.iter().flat_map(|numbox| numbox.cells()).map(|cell| (cell, Class::Part)));
+    classes.extend(others.iter().flat_map(|numbox| numbox.cells()).map(|cell| (cell, Class::Number)));
+    let mut gears = vec![];
+    for symbol in &symbols_boxes {
+        let class = match rule.ratio(symbol, &numbers, &neighbours)? {
+            Some(ratio) => {
+                gears.push((symbol, ratio));
+                Class::Gear
+            }
+            None => Class::Symbol,
+        };
+        classes.insert((symbol.x, symbol.y), class);
+    }
+
+    let mut out = String::new();
+    if *style == Style::Html {
+        out.push_str("<pre>\n");
+    }
+    for (y, line) in lines.iter().enumerate() {
+        let mut current = Class::Empty;
+        for (x, chr) in line.chars().enumerate() {
+            let class = classes.get(&(x, y)).copied().unwrap_or(Class::Empty);
+            if class != current {
+                out.push_str(style.close(current));
+                out.push_str(style.open(class));
+                current = class;
+            }
+            out.push_str(&style.escape(chr));
+        }
+        out.push_str(style.close(current));
+        out.push('\n');
+    }
+    if *style == Style::Html {
+        out.push_str("</pre>\n");
+    }
+
+    if legend && !gears.is_empty() {
+        let entries = gears.iter().map(|(gear, ratio)| {
+            let values: Vec<_> = get_number_neighbours(gear, &numbers, &neighbours).iter().map(|n| n.value.to_string()).collect();
+            format!("gear {} at line {}, column {}: {} => {}",
+                style.escape(gear.chr), gear.y + 1, gear.x + 1, values.join(", "), ratio)
+        });
+        match style {
+            Style::Ansi => {
+                out.push('\n');
+                entries.for_each(|entry| { out.push_str(&entry); out.push('\n'); });
+            }
+            Style::Html => {
+                out.push_str("<ul>\n");
+                entries.for_each(|entry| out.push_str(&format!("<li>{}</li>\n", entry)));
+                out.push_str("</ul>\n");
+            }
+        }
+    }
+    Ok(out)
+}
+
+// a schematic that can be edited cell by cell, keeping both sums up to date by
+// re-evaluating only the numbers and symbols around each edit
+struct Engine {
+    grid: Vec<Vec<char>>,
+    format: Format,
+    rule: GearRule,
+    neighbours: Neighbours,
+    // number boxes by their first cell, and whether each is a part number
+    numbers: HashMap<(usize, usize), (NumberBox, bool)>,
+    // the first cell of the number box covering each cell
+    covering: HashMap<(usize, usize), (usize, usize)>,
+    symbols: HashMap<(usize, usize), Point>,
+    // the ratio of every symbol that is a gear
+    gears: HashMap<(usize, usize), u64>,
+    part_sum: u64,
+    ratio_sum: u64,
+}
+
+impl Engine {
+    fn new(lines: Vec<&str>, format: &Format, rule: &GearRule, adjacency: &Adjacency) -> Result<Engine, String> {
+        let (width, height) = size(&lines);
+        let (numbers_boxes, symbols_boxes) = get_parts_and_symbols(lines.clone(), format)?;
+        let grid = lines.iter().map(|line| {
+            let mut row: Vec<char> = line.chars().collect();
+            row.resize(width, format.empty);
+            row
+        }).collect();
+
+        let mut engine = Engine {
+            grid,
+            format: format.clone(),
+            rule: rule.clone(),
+            neighbours: adjacency.on(width, height),
+            numbers: HashMap::new(),
+            covering: HashMap::new(),
+            symbols: symbols_boxes.into_iter().map(|symbol| ((symbol.x, symbol.y), symbol)).collect(),
+            gears: HashMap::new(),
+            part_sum: 0,
+            ratio_sum: 0,
+        };
+        let starts: Vec<_> = numbers_boxes.iter().map(|numbox| (numbox.numbox.0.x, numbox.numbox.0.y)).collect();
+        numbers_boxes.into_iter().for_each(|numbox| engine.insert_number(numbox));
+        engine.update_parts(&starts);
+        let mut cells: Vec<_> = engine.symbols.keys().copied().collect();
+        cells.sort();
+        engine.update_gears(&cells)?;
+        Ok(engine)
+    }
+
+    // the part number sum and the gear ratio sum
+    fn sums(&self) -> (u64, u64) {
+        (self.part_sum, self.ratio_sum)
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.grid.iter().map(|row| row.iter().collect()).collect()
+    }
+
+    // puts chr in the cell at column x of line y, both from zero; on error the
+    // schematic is left as it was
+    fn set(&mut self, x: usize, y: usize, chr: char) -> Result<(), String> {
+        let old = *self.grid.get(y).and_then(|row| row.get(x))
+            .ok_or_else(|| format!("no cell at line {}, column {}", y + 1, x + 1))?;
+        if old == chr {
+            return Ok(());
+        }
+        if chr.is_ascii_digit() {
+            let row = &self.grid[y];
+            let start = (0..x).rev().take_while(|i| row[*i].is_ascii_digit()).last().unwrap_or(x);
+            let value = (start..row.len()).map(|i| if i == x { chr } else { row[i] })
+                .take_while(char::is_ascii_digit)
+                .try_fold(0u32, |acc, digit| acc.checked_mul(10)?.checked_add(digit.to_digit(10)?));
+            if value.is_none() {
+                Err(format!("line {}, column {}: number too large", y + 1, start + 1))?
+            }
+        }
+        if let Err(e) = self.apply(x, y, chr) {
+            // the old contents evaluated fine before, so they do again
+            self.apply(x, y, old)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, x: usize, y: usize, chr: char) -> Result<(), String> {
+        // the numbers the edit may split, join, grow or shrink
+        let starts: Vec<_> = [x.checked_sub(1), Some(x), Some(x + 1)].into_iter().flatten()
+            .filter_map(|cx| self.covering.get(&(cx, y)).copied())
+            .collect();
+        let removed: Vec<_> = starts.into_iter().filter_map(|start| self.remove_number(start)).collect();
+
+        self.grid[y][x] = chr;
+        self.symbols.remove(&(x, y));
+        if !chr.is_ascii_digit() && chr != self.format.empty {
+            self.symbols.insert((x, y), Point {chr, x, y});
+        }
+
+        // read the digit runs back over the cells those numbers covered
+        let low = removed.iter().map(|numbox| numbox.numbox.0.x).fold(x, usize::min);
+        let high = removed.iter().map(|numbox| numbox.numbox.1.x).fold(x, usize::max);
+        let row = &self.grid[y];
+        let mut added = vec![];
+        let mut i = low;
+        while i <= high {
+            if !row[i].is_ascii_digit() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            let mut value = 0;
+            while i < row.len() && row[i].is_ascii_digit() {
+                value = value * 10 + row[i].to_digit(10).unwrap();
+                i += 1;
+            }
+            added.push(NumberBox { numbox: (Point {chr: ' ', x: start, y}, Point {chr: ' ', x: i - 1, y}), value });
+        }
+        added.into_iter().for_each(|numbox| self.insert_number(numbox));
+
+        // the new numbers, and any the edited cell may have made parts or stopped being parts
+        let mut starts: Vec<_> = self.neighbours.around(x, x, y).iter().filter_map(|cell| self.covering.get(cell).copied()).collect();
+        starts.sort();
+        starts.dedup();
+        self.update_parts(&starts);
+
+        // any symbol next to a number that came, went or changed, and the edited cell itself
+        let mut cells: Vec<_> = removed.iter()
+            .chain(starts.iter().map(|start| &self.numbers[start].0))
+            .flat_map(|numbox| self.neighbours.around(numbox.numbox.0.x, numbox.numbox.1.x, numbox.numbox.0.y))
+            .filter(|cell| self.symbols.contains_key(cell))
+            .chain([(x, y)])
+            .collect();
+        cells.sort();
+        cells.dedup();
+        self.update_gears(&cells)
+    }
+
+    fn insert_number(&mut self, numbox: NumberBox) {
+        let start = (numbox.numbox.0.x, numbox.numbox.0.y);
+        self.covering.extend(numbox.cells().map(|cell| (cell, start)));
+        self.numbers.insert(start, (numbox, false));
+    }
+
+    fn remove_number(&mut self, start: (usize, usize)) -> Option<NumberBox> {
+        let (numbox, part) = self.numbers.remove(&start)?;
+        numbox.cells().for_each(|cell| { self.covering.remove(&cell); });
+        if part {
+            self.part_sum -= numbox.value as u64;
+        }
+        Some(numbox)
+    }
+
+    // decides again whether each number starting at these cells is a part number
+    fn update_parts(&mut self, starts: &[(usize, usize)]) {
+        for start in starts {
+            let Some((numbox, part)) = self.numbers.get(start) else { continue };
+            let (first, last) = &numbox.numbox;
+            let symbols: SymbolIndex = self.neighbours.around(first.x, last.x, first.y).into_iter()
+                .filter_map(|cell| self.symbols.get(&cell).map(|symbol| (cell, symbol)))
+                .collect();
+            let now = !get_symbol_neighbours(numbox, &symbols, &self.neighbours).is_empty();
+            match (*part, now) {
+                (false, true) => self.part_sum += numbox.value as u64,
+                (true, false) => self.part_sum -= numbox.value as u64,
+                _ => continue,
+            }
+            self.numbers.get_mut(start).unwrap().1 = now;
+        }
+    }
+
+    // works out again the ratio of whichever of these cells hold gears
+    fn update_gears(&mut self, cells: &[(usize, usize)]) -> Result<(), String> {
+        let mut ratios = vec![];
+        for cell in cells {
+            let ratio = match self.symbols.get(cell) {
+                Some(symbol) => {
+                    let numbers: NumberIndex = self.neighbours.around(symbol.x, symbol.x, symbol.y).into_iter()
+                        .filter_map(|cell| self.covering.get(&cell).map(|start| (cell, &self.numbers[start])))
+                        .filter(|(_, (_, part))| *part)
+                        .map(|(cell, (numbox, _))| (cell, numbox))
+                        .collect();
+                    self.rule.ratio(symbol, &numbers, &self.neighbours)?
+                }
+                None => None,
+            };
+            ratios.push((*cell, ratio));
+        }
+        // the new sum is worked out before any gear changes, so an error leaves them as they were
+        let mut ratio_sum = self.ratio_sum - cells.iter().filter_map(|cell| self.gears.get(cell)).sum::<u64>();
+        for (cell, ratio) in &ratios {
+            if let Some(ratio) = ratio {
+                ratio_sum = ratio_sum.checked_add(*ratio)
+                    .ok_or_else(|| format!("line {}, column {}: gear ratio sum too large", cell.1 + 1, cell.0 + 1))?;
+            }
+        }
+        for (cell, ratio) in ratios {
+            self.gears.remove(&cell);
+            if let Some(ratio) = ratio {
+                self.gears.insert(cell, ratio);
+            }
+        }
+        self.ratio_sum = ratio_sum;
+        Ok(())
+    }
+}
+
+// a dense, reproducible schematic with plenty of gears, for benchmarking
+fn generate_schematic(width: usize, height: usize) -> String {
+    let mut state: u64 = 0x9E3779B97F4A7C15;
+    let mut next = move || { state ^= state << 13; state ^= state >> 7; state ^= state << 17; state };
+    (0..height).map(|_| {
+        let mut line = String::new();
+        while line.len() < width {
+            match next() % 10 {
+                0 => line.push('*'),
+                1 => line.push('#'),
+                2..=4 => line.push_str(&(next() % 1000).to_string()),
+                _ => line.push('.'),
+            }
+            line.push('.');
+        }
+        line.truncate(width);
+        line
+    }).collect::<Vec<_>>().join("\n")
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 struct Point {
     chr: char,
@@ -40,45 +578,200 @@ struct NumberBox {
     value: u32,
 }
 
-fn get_parts_and_symbols(lines: Vec<&str>) -> (Vec<NumberBox>, Vec<Point>) {
+impl NumberBox {
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.numbox.0.x..=self.numbox.1.x).map(move |x| (x, self.numbox.0.y))
+    }
+}
 
-    let symbols: Vec<_> = lines.iter().enumerate().flat_map(|(l, line)| 
-        line.trim().chars().enumerate().filter_map(move| (c, chr)| 
-            if chr != '.' && !chr.is_digit(10) {
-                Some(Point {chr: chr, x: c, y: l})
-            } else { None }
-        )
-    ).collect();
+// how a schematic is laid out: the character marking an empty cell, and whether
+// short lines are padded out with empty cells rather than rejected
+#[derive(Clone, Debug)]
+struct Format {
+    empty: char,
+    pad: bool,
+}
 
-    let re = Regex::new(r"([0-9]+)").unwrap();
+impl Default for Format {
+    fn default() -> Self {
+        Format { empty: '.', pad: false }
+    }
+}
 
-    let numboxes: Vec<_> = lines.iter().enumerate().flat_map(|(l, line)|
-        re.find_iter(line.trim()).map(move |mtch| NumberBox {
-            numbox: (Point {chr: ' ', x: mtch.start(), y: l}, Point {chr: ' ', x: mtch.end() - 1, y: l}), value: u32::from_str(mtch.as_str()).unwrap()
-        })
-    ).collect();
+fn get_parts_and_symbols(lines: Vec<&str>, format: &Format) -> Result<(Vec<NumberBox>, Vec<Point>), String> {
+    if format.empty.is_ascii_digit() {
+        Err(format!("'{}' cannot mark empty cells, it is a digit", format.empty))?
+    }
+    let width = lines.first().map_or(0, |line| line.chars().count());
+    if !format.pad {
+        if let Some((l, line)) = lines.iter().enumerate().find(|(_, line)| line.chars().count() != width) {
+            Err(format!("line {} is {} cells wide, but line 1 is {}", l + 1, line.chars().count(), width))?
+        }
+    }
+
+    let mut numboxes = vec![];
+    let mut symbols = vec![];
+    for (y, line) in lines.iter().enumerate() {
+        // the start column and value of the number being read
+        let mut number: Option<(usize, u32)> = None;
+        for (x, chr) in line.chars().enumerate() {
+            if let Some(digit) = chr.to_digit(10) {
+                let (start, value) = number.unwrap_or((x, 0));
+                let value = value.checked_mul(10).and_then(|v| v.checked_add(digit))
+                    .ok_or_else(|| format!("line {}, column {}: number too large", y + 1, start + 1))?;
+                number = Some((start, value));
+                continue;
+            }
+            if let Some((start, value)) = number.take() {
+                numboxes.push(NumberBox { numbox: (Point {chr: ' ', x: start, y}, Point {chr: ' ', x: x - 1, y}), value });
+            }
+            if chr != format.empty {
+                symbols.push(Point {chr, x, y});
+            }
+        }
+        if let Some((start, value)) = number {
+            let end = line.chars().count() - 1;
+            numboxes.push(NumberBox { numbox: (Point {chr: ' ', x: start, y}, Point {chr: ' ', x: end, y}), value });
+        }
+    }
+
+    Ok((numboxes, symbols))
+}
+
+// the symbols by cell
+type SymbolIndex<'a> = HashMap<(usize, usize), &'a Point>;
 
-    (numboxes, symbols)
+// each number box under every cell it covers
+type NumberIndex<'a> = HashMap<(usize, usize), &'a NumberBox>;
+
+fn index_symbols(symbols: &[Point]) -> SymbolIndex<'_> {
+    symbols.iter().map(|symbol| ((symbol.x, symbol.y), symbol)).collect()
 }
 
-fn get_symbol_neighbours<'a>(numbox: &&NumberBox, symbols: &'a Vec<Point>) -> Vec<&'a Point> {
-    symbols.iter().filter(|symbol| {
-        symbol.x as i32 >= (numbox.numbox.0.x as i32) - 1 && symbol.x <= numbox.numbox.1.x + 1 &&
-        symbol.y as i32 >= (numbox.numbox.0.y as i32) - 1 && symbol.y <= numbox.numbox.1.y + 1
-    }).collect()
+fn index_numbers<'a>(numboxes: &[&'a NumberBox]) -> NumberIndex<'a> {
+    numboxes.iter().flat_map(|numbox| numbox.cells().map(move |cell| (cell, *numbox))).collect()
 }
 
-fn get_number_neighbours<'a>(symbol: &&Point, numboxes: &'a Vec<&NumberBox>) -> Vec<&'a &'a NumberBox> {
-    numboxes.iter().filter(|numbox| {
-        symbol.x as i32 >= (numbox.numbox.0.x as i32) - 1 && symbol.x <= numbox.numbox.1.x + 1 &&
-        symbol.y as i32 >= (numbox.numbox.0.y as i32) - 1 && symbol.y <= numbox.numbox.1.y + 1
-    }).collect()
+// which cells count as touching a number or a symbol
+#[derive(Clone, Debug, PartialEq)]
+enum Neighbourhood {
+    // sharing an edge
+    Four,
+    // sharing an edge or a corner, as in the puzzle
+    Eight,
+    // within k steps along the rows and columns
+    Manhattan(usize),
+}
+
+impl FromStr for Neighbourhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "4" => Ok(Neighbourhood::Four),
+            None if s == "8" => Ok(Neighbourhood::Eight),
+            Some(("manhattan", k)) => k.parse().map(Neighbourhood::Manhattan)
+                .map_err(|e| format!("invalid radius '{}' in '{}': {}", k, s, e)),
+            _ => Err(format!("unknown neighbourhood '{}', expected 4, 8 or manhattan:K", s)),
+        }
+    }
+}
+
+// the neighbourhood, and whether it wraps around the edges of the schematic
+#[derive(Clone, Debug, PartialEq)]
+struct Adjacency {
+    neighbourhood: Neighbourhood,
+    wrap: bool,
+}
+
+impl Default for Adjacency {
+    fn default() -> Self {
+        Adjacency { neighbourhood: Neighbourhood::Eight, wrap: false }
+    }
+}
+
+impl Adjacency {
+    // the adjacency on a schematic of this size
+    fn on(&self, width: usize, height: usize) -> Neighbours {
+        // no two cells are more than width + height apart, so a bigger radius
+        // reaches nothing more
+        let reach = match self.neighbourhood {
+            Neighbourhood::Four | Neighbourhood::Eight => 1,
+            Neighbourhood::Manhattan(k) => isize::try_from(k.min(width.saturating_add(height))).unwrap_or(isize::MAX),
+        };
+        // lines a height or more away are off the schematic, or, wrapping,
+        // the same lines as nearer ones that reach further
+        let spans = (-reach..=reach)
+            .filter(|dy| dy.unsigned_abs() < height)
+            .map(|dy| match self.neighbourhood {
+                Neighbourhood::Eight => (dy, 1),
+                Neighbourhood::Four | Neighbourhood::Manhattan(_) => (dy, reach - dy.abs()),
+            })
+            .collect();
+        Neighbours { spans, width, height, wrap: self.wrap }
+    }
+}
+
+// an adjacency fixed to the size of one schematic
+#[derive(Clone, Debug)]
+struct Neighbours {
+    // each line offset reached, and how many columns either side it reaches
+    spans: Vec<(isize, isize)>,
+    width: usize,
+    height: usize,
+    wrap: bool,
+}
+
+impl Neighbours {
+    // every cell touching the columns x0 to x1 of line y, including those
+    // columns themselves, in reading order
+    fn around(&self, x0: usize, x1: usize, y: usize) -> Vec<(usize, usize)> {
+        let (width, height) = (self.width as isize, self.height as isize);
+        if !self.wrap {
+            return self.spans.iter()
+                .map(|&(dy, reach)| (y as isize + dy, reach))
+                .filter(|(y, _)| (0..height).contains(y))
+                .flat_map(|(y, reach)| {
+                    let (from, to) = ((x0 as isize).saturating_sub(reach).max(0), (x1 as isize).saturating_add(reach).min(width - 1));
+                    (from..=to).map(move |x| (x as usize, y as usize))
+                })
+                .collect();
+        }
+        // a span as wide as the schematic takes in the whole line
+        let mut cells: Vec<_> = self.spans.iter().flat_map(|&(dy, reach)| {
+            let y = (y as isize + dy).rem_euclid(height) as usize;
+            let (from, to) = match (x0 as isize).checked_sub(reach).zip((x1 as isize).checked_add(reach)) {
+                Some((from, to)) if to - from < width => (from, to),
+                _ => (0, width - 1),
+            };
+            (from..=to).map(move |x| (x.rem_euclid(width) as usize, y))
+        }).collect();
+        cells.sort_by_key(|(x, y)| (*y, *x));
+        cells.dedup();
+        cells
+    }
+}
+
+// in reading order, like the schematic itself
+fn get_symbol_neighbours<'a>(numbox: &NumberBox, symbols: &SymbolIndex<'a>, neighbours: &Neighbours) -> Vec<&'a Point> {
+    let (start, end) = &numbox.numbox;
+    neighbours.around(start.x, end.x, start.y).iter().filter_map(|cell| symbols.get(cell).copied()).collect()
+}
+
+// in reading order of where the numbers start
+fn get_number_neighbours<'a>(symbol: &Point, numboxes: &NumberIndex<'a>, neighbours: &Neighbours) -> Vec<&'a NumberBox> {
+    let mut neighbours: Vec<&NumberBox> = neighbours.around(symbol.x, symbol.x, symbol.y).iter()
+        .filter_map(|cell| numboxes.get(cell).copied())
+        .collect();
+    neighbours.sort_by_key(|numbox| (numbox.numbox.0.y, numbox.numbox.0.x));
+    neighbours.dedup_by_key(|numbox| (numbox.numbox.0.y, numbox.numbox.0.x));
+    neighbours
 }
 
 #[cfg(test)]
 mod tests_p1 {
 
-    use crate::{get_parts_and_symbols, get_symbol_neighbours, Point, NumberBox, get_number_neighbours};
+    use crate::{get_parts_and_symbols, get_symbol_neighbours, Point, NumberBox, get_number_neighbours, index_symbols, index_numbers, solve, generate_schematic, Format, GearRule, Count, Aggregate, render, Style, Engine, Adjacency, Neighbourhood};
 
     #[test]
     fn test_example_sum() -> Result<(), String> {
@@ -93,7 +786,9 @@ mod tests_p1 {
         ......755.
         ...$.*....
         .664.598..".trim();
-        let (parts, symbs) = get_parts_and_symbols(s.lines().collect());
+        let (parts, symbs) = get_parts_and_symbols(s.lines().map(str::trim).collect(), &Format::default())?;
+        let symbol_index = index_symbols(&symbs);
+        let neighbours = Adjacency::default().on(10, 10);
 
         assert!(!parts.is_empty());
         assert_eq!(parts.len(), 10);
@@ -109,18 +804,19 @@ mod tests_p1 {
         ]);
 
         {
-            let regression_467 = parts.iter().filter(|p| p.value == 467).next().unwrap();
+            let regression_467 = parts.iter().find(|p| p.value == 467).unwrap();
             assert_eq!(regression_467.numbox, (Point{chr: ' ', x:0, y:0}, Point{chr: ' ', x:2, y:0}));
-            assert_eq!(get_symbol_neighbours(&regression_467, &symbs), vec!(&Point{chr: '*', x:3,y:1}));
+            assert_eq!(get_symbol_neighbours(regression_467, &symbol_index, &neighbours), vec!(&Point{chr: '*', x:3,y:1}));
         }
 
         {
-            let regression_755 = parts.iter().filter(|p| p.value == 755).next().unwrap();
+            let regression_755 = parts.iter().find(|p| p.value == 755).unwrap();
             assert_eq!(regression_755.numbox, (Point{chr: ' ', x:6, y:7}, Point{chr: ' ', x:8, y:7}));
             let expected: Vec<&Point> = vec!(&Point{chr: '*', x:5, y:8});
-            assert_eq!(crate::get_symbol_neighbours(&regression_755, &symbs), expected);
+            assert_eq!(crate::get_symbol_neighbours(regression_755, &symbol_index, &neighbours), expected);
         }
-        let kept: Vec<_> = parts.iter().filter(|part| get_symbol_neighbours(part, &symbs).len() > 0).collect();
+        let kept: Vec<_> = parts.iter().filter(|part| !get_symbol_neighbours(part, &symbol_index, &neighbours).is_empty()).collect();
+        let number_index = index_numbers(&kept);
         {
             let expected = vec!(
                 &NumberBox { numbox: (Point { chr: ' ', x: 0, y: 0 }, Point { chr: ' ', x: 2, y: 0 }), value: 467 }, 
@@ -142,7 +838,7 @@ mod tests_p1 {
         {
             let gears: Vec<_> = symbs.iter()
                 .filter(|s| s.chr == '*')
-                .filter(|s| get_number_neighbours(s, &kept).len() == 2)
+                .filter(|s| get_number_neighbours(s, &number_index, &neighbours).len() == 2)
                 .collect();
 
             let expected: Vec<&Point> = vec!(
@@ -152,7 +848,7 @@ mod tests_p1 {
             assert_eq!(gears, expected);
 
             let ratios: Vec<Vec<_>> = gears.iter()
-                .map(|g| get_number_neighbours(g, &kept).iter().map(|n| n.value).collect())
+                .map(|g| get_number_neighbours(g, &number_index, &neighbours).iter().map(|n| n.value).collect())
                 .collect();
 
             assert_eq!(ratios, vec!(vec!(467, 35), vec!(755, 598)));
@@ -166,4 +862,203 @@ mod tests_p1 {
 
         Ok(())
     }
+
+    // the original scan over every symbol for every number, and vice versa
+    fn brute_force(lines: Vec<&str>) -> (u64, u64) {
+        let (parts, symbs) = get_parts_and_symbols(lines, &Format::default()).unwrap();
+        let touches = |numbox: &NumberBox, symbol: &Point| {
+            symbol.x as i32 >= (numbox.numbox.0.x as i32) - 1 && symbol.x <= numbox.numbox.1.x + 1 &&
+            symbol.y as i32 >= (numbox.numbox.0.y as i32) - 1 && symbol.y <= numbox.numbox.1.y + 1
+        };
+        let kept: Vec<_> = parts.iter().filter(|part| symbs.iter().any(|s| touches(part, s))).collect();
+        let ratio_sum = symbs.iter().filter(|s| s.chr == '*').map(|s| {
+            let neighbours: Vec<_> = kept.iter().filter(|n| touches(n, s)).collect();
+            if neighbours.len() == 2 { neighbours.iter().map(|n| n.value as u64).product() } else { 0 }
+        }).sum();
+        (kept.iter().map(|n| n.value as u64).sum(), ratio_sum)
+    }
+
+    #[test]
+    fn test_index_matches_scan() {
+        let schematic = generate_schematic(150, 150);
+        assert_eq!(solve(schematic.lines().collect(), &Format::default(), &GearRule::default(), &Adjacency::default()), Ok(brute_force(schematic.lines().collect())));
+    }
+
+    #[test]
+    fn test_columns_and_shape() {
+        // leading blanks are cells too, so they shift nothing
+        let spaced = Format { empty: ' ', pad: false };
+        let (parts, symbs) = get_parts_and_symbols(vec!("  12 ", "   # "), &spaced).unwrap();
+        assert_eq!(parts[0].numbox, (Point{chr: ' ', x:2, y:0}, Point{chr: ' ', x:3, y:0}));
+        assert_eq!(symbs, [Point{chr: '#', x:3, y:1}]);
+
+        // a number running to the end of its line
+        let (parts, _) = get_parts_and_symbols(vec!("..7", "*.."), &Format::default()).unwrap();
+        assert_eq!(parts[0].numbox, (Point{chr: ' ', x:2, y:0}, Point{chr: ' ', x:2, y:0}));
+
+        let ragged = vec!("467..", "...*", "..35.");
+        assert_eq!(get_parts_and_symbols(ragged.clone(), &Format::default()), Err("line 2 is 4 cells wide, but line 1 is 5".to_string()));
+        let padded = Format { pad: true, ..Format::default() };
+        assert_eq!(solve(ragged, &padded, &GearRule::default(), &Adjacency::default()), Ok((502, 467 * 35)));
+
+        assert!(get_parts_and_symbols(vec!("1.1"), &Format { empty: '1', pad: false }).is_err());
+        assert!(get_parts_and_symbols(vec!("99999999999"), &Format::default()).is_err());
+    }
+
+    const SAMPLE: &str = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+
+    #[test]
+    fn test_gear_rules() -> Result<(), String> {
+        let ratio_sum = |symbols: &str, count: &str, aggregate: &str| -> Result<u64, String> {
+            let rule = GearRule { symbols: symbols.chars().collect(), count: count.parse()?, aggregate: aggregate.parse()? };
+            Ok(solve(SAMPLE.lines().collect(), &Format::default(), &rule, &Adjacency::default())?.1)
+        };
+        assert_eq!(ratio_sum("*", "2", "product")?, 467835);
+        assert_eq!(ratio_sum("*", "1+", "sum")?, 467 + 35 + 617 + 755 + 598);
+        assert_eq!(ratio_sum("*#+$", "1", "max")?, 633 + 617 + 592 + 664);
+        assert_eq!(ratio_sum("*", "1-2", "max")?, 467 + 617 + 755);
+        assert_eq!(ratio_sum("*", "3+", "product")?, 0);
+
+        // a lone * allowed no numbers adds nothing, whatever the aggregate
+        for aggregate in ["product", "sum", "max"] {
+            let rule = GearRule { count: "0+".parse()?, aggregate: aggregate.parse()?, ..GearRule::default() };
+            assert_eq!(solve(vec!("12#..", "....*"), &Format::default(), &rule, &Adjacency::default()), Ok((12, 0)));
+        }
+
+        assert_eq!("2-4".parse(), Ok(Count::Range(2, 4)));
+        assert!("4-2".parse::<Count>().is_err());
+        assert!("two".parse::<Count>().is_err());
+        assert!("mean".parse::<Aggregate>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_render() -> Result<(), String> {
+        let ansi = render(SAMPLE.lines().collect(), &Format::default(), &GearRule::default(), &Adjacency::default(), &Style::Ansi, true)?;
+        let lines: Vec<_> = ansi.lines().collect();
+        assert_eq!(lines[0], "\x1b[32m467\x1b[0m..\x1b[31m114\x1b[0m..");
+        assert_eq!(lines[1], "...\x1b[1;35m*\x1b[0m......");
+        assert_eq!(lines[4], "\x1b[32m617\x1b[0m\x1b[33m*\x1b[0m......");
+        assert_eq!(&lines[10..], ["", "gear * at line 2, column 4: 467, 35 => 16345", "gear * at line 9, column 6: 755, 598 => 451490"]);
+
+        let html = render(vec!("1<.", "..&"), &Format::default(), &GearRule::default(), &Adjacency::default(), &Style::Html, false)?;
+        assert_eq!(html, "<pre>\n<span style=\"color:green\">1</span><span style=\"color:olive\">&lt;</span>.\n..<span style=\"color:olive\">&amp;</span>\n</pre>\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_engine_matches_recomputation() {
+        let schematic = generate_schematic(40, 40);
+        let chars: Vec<char> = "0123456789....*#".chars().collect();
+        let mut state: u64 = 0x2545F4914F6CDD1D;
+        let mut next = move || { state ^= state << 13; state ^= state >> 7; state ^= state << 17; state };
+        for adjacency in [Adjacency::default(), Adjacency { neighbourhood: Neighbourhood::Manhattan(2), wrap: true }] {
+            let mut engine = Engine::new(schematic.lines().collect(), &Format::default(), &GearRule::default(), &adjacency).unwrap();
+            for _ in 0..500 {
+                let (x, y, chr) = (next() as usize % 40, next() as usize % 40, chars[next() as usize % chars.len()]);
+                if engine.set(x, y, chr).is_err() {
+                    continue;
+                }
+                let lines = engine.lines();
+                assert_eq!(Ok(engine.sums()), solve(lines.iter().map(String::as_str).collect(), &Format::default(), &GearRule::default(), &adjacency));
+            }
+        }
+    }
+
+    #[test]
+    fn test_engine_rejects_edits() -> Result<(), String> {
+        let rule = GearRule { count: "1+".parse()?, ..GearRule::default() };
+        let lines = vec!("1000000000*1000000000", "............00000000.");
+        let mut engine = Engine::new(lines.clone(), &Format::default(), &rule, &Adjacency::default())?;
+        assert_eq!(engine.sums(), (2000000000, 1000000000000000000));
+
+        // joining up 100000000 makes the gear's ratio overflow
+        assert!(engine.set(11, 1, '1').is_err());
+        assert!(engine.set(0, 0, '9').is_err());
+        assert!(engine.set(21, 0, '.').is_err());
+        assert_eq!(engine.lines(), lines);
+        assert_eq!(engine.sums(), (2000000000, 1000000000000000000));
+
+        engine.set(10, 1, '#')?;
+        assert_eq!(engine.sums(), (2000000000, 1000000000000000000));
+
+        // each ratio fits, but two of them don't add up to a u64
+        let lines = vec!("4000000000*4000000000", ".....................", "4000000000*4000000000");
+        let error = "line 3, column 11: gear ratio sum too large".to_owned();
+        let (format, rule, adjacency) = (Format::default(), GearRule::default(), Adjacency::default());
+        assert_eq!(solve(lines.clone(), &format, &rule, &adjacency), Err(error.clone()));
+        assert_eq!(Engine::new(lines, &format, &rule, &adjacency).err(), Some(error));
+
+        let lines = vec!("4000000000*4000000000", ".....................", "4000000000*400000000.");
+        let mut engine = Engine::new(lines.clone(), &format, &rule, &adjacency)?;
+        assert_eq!(engine.sums(), (12400000000, 17600000000000000000));
+        assert_eq!(engine.set(20, 2, '0'), Err("line 3, column 11: gear ratio sum too large".to_owned()));
+        assert_eq!(engine.lines(), lines);
+        assert_eq!(engine.sums(), (12400000000, 17600000000000000000));
+        Ok(())
+    }
+
+    // whether a number and a symbol touch, by their distance apart
+    fn touches(numbox: &NumberBox, symbol: &Point, adjacency: &Adjacency, width: usize, height: usize) -> bool {
+        let distance = |a: usize, b: usize, size: usize| {
+            let d = a.abs_diff(b);
+            if adjacency.wrap { d.min(size - d) } else { d }
+        };
+        numbox.cells().any(|(x, y)| {
+            let (dx, dy) = (distance(x, symbol.x, width), distance(y, symbol.y, height));
+            match adjacency.neighbourhood {
+                Neighbourhood::Four => dx + dy <= 1,
+                Neighbourhood::Eight => dx.max(dy) <= 1,
+                Neighbourhood::Manhattan(k) => dx + dy <= k,
+            }
+        })
+    }
+
+    #[test]
+    fn test_adjacency_matches_distance() {
+        let schematic = generate_schematic(30, 20);
+        let lines: Vec<_> = schematic.lines().collect();
+        let (parts, symbs) = get_parts_and_symbols(lines.clone(), &Format::default()).unwrap();
+        for neighbourhood in ["4", "8", "manhattan:2", "manhattan:3"] {
+            for wrap in [false, true] {
+                let adjacency = Adjacency { neighbourhood: neighbourhood.parse().unwrap(), wrap };
+                let kept: Vec<_> = parts.iter().filter(|part| symbs.iter().any(|s| touches(part, s, &adjacency, 30, 20))).collect();
+                let ratio_sum = symbs.iter().filter(|s| s.chr == '*').map(|s| {
+                    let neighbours: Vec<_> = kept.iter().filter(|n| touches(n, s, &adjacency, 30, 20)).collect();
+                    if neighbours.len() == 2 { neighbours.iter().map(|n| n.value as u64).product() } else { 0 }
+                }).sum();
+                let expected = (kept.iter().map(|n| n.value as u64).sum(), ratio_sum);
+                assert_eq!(solve(lines.clone(), &Format::default(), &GearRule::default(), &adjacency), Ok(expected), "{} wrap {}", neighbourhood, wrap);
+            }
+        }
+
+        // the * reaches the numbers two steps away, or one step across the edges
+        let corner = vec!("*.2", "...", "3..");
+        let sums = |neighbourhood: &str, wrap| solve(corner.clone(), &Format::default(), &GearRule { count: "1+".parse().unwrap(), ..GearRule::default() },
+            &Adjacency { neighbourhood: neighbourhood.parse().unwrap(), wrap });
+        assert_eq!(sums("8", false), Ok((0, 0)));
+        assert_eq!(sums("4", false), Ok((0, 0)));
+        assert_eq!(sums("manhattan:2", false), Ok((5, 6)));
+        assert_eq!(sums("4", true), Ok((5, 6)));
+        // radii past the size of the schematic reach everything, and no further
+        let lines: Vec<_> = SAMPLE.lines().collect();
+        for wrap in [false, true] {
+            let huge = Adjacency { neighbourhood: format!("manhattan:{}", usize::MAX).parse().unwrap(), wrap };
+            let all = Adjacency { neighbourhood: Neighbourhood::Manhattan(20), wrap };
+            assert_eq!(solve(lines.clone(), &Format::default(), &GearRule::default(), &huge), Ok((4533, 0)));
+            assert_eq!(solve(lines.clone(), &Format::default(), &GearRule::default(), &all), Ok((4533, 0)));
+            assert_eq!(huge.on(10, 10).spans, all.on(10, 10).spans);
+        }
+        assert!("6".parse::<Neighbourhood>().is_err());
+        assert!("manhattan:x".parse::<Neighbourhood>().is_err());
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::{error::Error, fs, str::FromStr};
 
 use nom::bytes::complete::*;
@@ -6,25 +7,70 @@ use nom::multi::*;
 use nom::sequence::*;
 use nom::{character::complete::*, combinator::*, IResult};
 
+/// How repeated numbers count. As a multiset, each copy of a number you have
+/// needs its own copy among the winning numbers; as a set, a number matches
+/// at most once however often it's repeated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Matching {
+    Multiset,
+    Set,
+}
+
+impl FromStr for Matching {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "multiset" => Ok(Matching::Multiset),
+            "set" => Ok(Matching::Set),
+            _ => Err(format!("unknown matching '{}', expected multiset or set", s)),
+        }
+    }
+}
+
+fn count_matches(winning: &[u32], have: &[u32], matching: Matching) -> usize {
+    match matching {
+        Matching::Set => {
+            let winning: HashSet<_> = winning.iter().collect();
+            have.iter().collect::<HashSet<_>>().intersection(&winning).count()
+        },
+        Matching::Multiset => {
+            let mut unmatched: HashMap<_, usize> = HashMap::new();
+            winning.iter().for_each(|n| *unmatched.entry(n).or_default() += 1);
+            have.iter().filter(|n| match unmatched.get_mut(n) {
+                Some(left) if *left > 0 => { *left -= 1; true },
+                _ => false,
+            }).count()
+        },
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 struct Card {
     id: usize,
     winning: Vec<u32>,
     have: Vec<u32>,
     matches: usize,
-    score: u32,
 }
 
 impl Card {
     fn new(id: usize, winning: Vec<u32>, have: Vec<u32>) -> Card {
-        let matches = have.iter().filter(|h| winning.contains(h)).count();
-        Card {
-            id: id,
-            winning: winning,
-            have: have,
-            matches: matches,
-            score: if matches == 0 { 0 } else { 1 << matches - 1 },
+        Card { id, winning, have, matches: 0 }.with_matching(Matching::Multiset)
+    }
+
+    fn with_matching(self, matching: Matching) -> Card {
+        Card { matches: count_matches(&self.winning, &self.have, matching), ..self }
+    }
+
+    // the first number repeated within either list
+    fn validate(&self) -> Result<(), String> {
+        for (list, numbers) in [("winning numbers", &self.winning), ("numbers you have", &self.have)] {
+            let mut seen = HashSet::new();
+            if let Some(n) = numbers.iter().find(|n| !seen.insert(*n)) {
+                return Err(format!("card {}: {} appears more than once in the {}", self.id, n, list));
+            }
         }
+        Ok(())
     }
 
     fn parse_number(input: &str) -> IResult<&str, u32> {
@@ -69,26 +115,259 @@ impl FromStr for Card {
     }
 }
 
-// not memoizing this is fast enough
-fn transitive_value_of_card(card: &Card, set: &Vec<Card>) -> u32 {
-    1 + (card.id..card.id + card.matches)
-        .map(|c| transitive_value_of_card(&set[c], set))
-        .sum::<u32>()
+/// What happens when a card wins copies of cards past the last one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Overflow {
+    // those copies are never won
+    Clamp,
+    Error,
+    // they're copies of the cards from the start of the table again
+    Wrap,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(Overflow::Clamp),
+            "error" => Ok(Overflow::Error),
+            "wrap" => Ok(Overflow::Wrap),
+            _ => Err(format!("unknown overflow policy '{}', expected clamp, error or wrap", s)),
+        }
+    }
+}
+
+/// How many points a card with some number of matches is worth.
+#[derive(Clone, Debug, PartialEq)]
+enum Scoring {
+    // 1 for the first match, then doubled for each one after it
+    Doubling,
+    // 1 per match
+    Linear,
+    // 1, 1, 2, 3, 5, ...
+    Fibonacci,
+    // the score for 0, 1, 2, ... matches, with the last entry standing in
+    // for any more matches than that
+    Table(Vec<u64>),
+}
+
+impl Scoring {
+    // None once the score no longer fits in a u64
+    fn score(&self, matches: usize) -> Option<u64> {
+        match self {
+            Scoring::Doubling => match matches {
+                0 => Some(0),
+                _ => u32::try_from(matches - 1).ok().and_then(|shift| 1u64.checked_shl(shift)),
+            },
+            Scoring::Linear => Some(matches as u64),
+            // the next number may not fit even when this one does
+            Scoring::Fibonacci => (0..matches)
+                .try_fold((0u64, Some(1u64)), |(a, b), _| b.map(|b| (b, a.checked_add(b))))
+                .map(|(a, _)| a),
+            Scoring::Table(scores) => Some(scores.get(matches).or(scores.last()).copied().unwrap_or(0)),
+        }
+    }
+}
+
+// doubling, linear, fibonacci or table:0,1,3,7
+impl FromStr for Scoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "doubling" => Ok(Scoring::Doubling),
+            None if s == "linear" => Ok(Scoring::Linear),
+            None if s == "fibonacci" => Ok(Scoring::Fibonacci),
+            Some(("table", scores)) => scores.split(',')
+                .map(|n| n.parse().map_err(|e| format!("invalid score '{}' in '{}': {}", n, s, e)))
+                .collect::<Result<_, _>>()
+                .map(Scoring::Table),
+            _ => Err(format!("unknown scoring '{}', expected doubling, linear, fibonacci or table:...", s)),
+        }
+    }
+}
+
+/// Which cards a card wins copies of: the `matches` cards after it, skipping
+/// `offset` cards first, and no more than `cap` of them.
+#[derive(Clone, Debug, PartialEq)]
+struct Cascade {
+    offset: usize,
+    cap: Option<usize>,
+    overflow: Overflow,
+}
+
+impl From<Overflow> for Cascade {
+    fn from(overflow: Overflow) -> Cascade {
+        Cascade { offset: 0, cap: None, overflow }
+    }
+}
+
+type Cards = BTreeMap<usize, Card>;
+
+// the cards by id, which have to run from 1 with nothing missing or repeated
+fn index_cards(cards: Vec<Card>) -> Result<Cards, String> {
+    let mut table = Cards::new();
+    for card in cards {
+        if table.contains_key(&card.id) {
+            return Err(format!("card {} appears more than once", card.id));
+        }
+        table.insert(card.id, card);
+    }
+    if let Some(missing) = (1..=table.len()).find(|id| !table.contains_key(id)) {
+        return Err(format!("card {} is missing", missing));
+    }
+    Ok(table)
+}
+
+// the ids of the cards a card wins copies of
+fn won_ids(card: &Card, last: usize, cascade: &Cascade) -> Result<Vec<usize>, String> {
+    let wins = cascade.cap.map_or(card.matches, |cap| card.matches.min(cap));
+    let first = card.id + 1 + cascade.offset;
+    (first..first + wins).filter_map(|id| match cascade.overflow {
+        _ if id <= last => Some(Ok(id)),
+        Overflow::Clamp => None,
+        Overflow::Error => Some(Err(format!("card {} wins copies of card {}, but the last card is {}", card.id, id, last))),
+        Overflow::Wrap => Some(Ok((id - 1) % last + 1)),
+    }).collect()
+}
+
+/// The cascade played out: which cards each card wins copies of, and how many
+/// copies of every card there are at the end, both by id.
+#[derive(Debug, PartialEq)]
+struct WinTree {
+    won: BTreeMap<usize, Vec<usize>>,
+    copies: BTreeMap<usize, u64>,
+}
+
+impl WinTree {
+    // A card's copies are all known once every card that wins copies of it has
+    // been counted, so the cards are taken in that order (which is just by id
+    // unless wins wrap around) and pass their copies on.
+    fn new(cards: &Cards, cascade: &Cascade) -> Result<WinTree, String> {
+        let last = cards.len();
+        let won = cards.values().map(|card| Ok((card.id, won_ids(card, last, cascade)?))).collect::<Result<BTreeMap<_, _>, String>>()?;
+
+        let mut winners = vec![0usize; last + 1];
+        won.values().flatten().for_each(|id| winners[*id] += 1);
+
+        let mut copies: BTreeMap<usize, u64> = cards.keys().map(|id| (*id, 1)).collect();
+        let mut ready: VecDeque<usize> = cards.keys().copied().filter(|id| winners[*id] == 0).collect();
+        let mut counted = 0;
+        while let Some(id) = ready.pop_front() {
+            counted += 1;
+            for won_id in &won[&id] {
+                let total = copies[won_id].checked_add(copies[&id])
+                    .ok_or_else(|| format!("card {} ends up with too many copies", won_id))?;
+                copies.insert(*won_id, total);
+                winners[*won_id] -= 1;
+                if winners[*won_id] == 0 {
+                    ready.push_back(*won_id);
+                }
+            }
+        }
+        if counted < last {
+            return Err("the wins wrap around into a loop, so the cards never run out".to_owned());
+        }
+        // checked once here, so `total` can't overflow
+        if copies.values().try_fold(0u64, |sum, n| sum.checked_add(*n)).is_none() {
+            return Err("the cards end up with too many copies between them".to_owned());
+        }
+        Ok(WinTree { won, copies })
+    }
+
+    fn total(&self) -> u64 {
+        self.copies.values().sum()
+    }
+
+    // the copies a card won directly: one of each card it wins, per copy of it
+    fn spawned(&self, id: usize) -> u64 {
+        self.copies[&id] * self.won[&id].len() as u64
+    }
+
+    // each edge is labelled with the copies it carries
+    fn dot(&self) -> String {
+        let mut lines = vec!["digraph cards {".to_owned()];
+        for (id, copies) in &self.copies {
+            lines.push(format!("  {} [label=\"Card {}\\n{} copies\"];", id, id, copies));
+        }
+        for (id, won) in &self.won {
+            for won_id in won {
+                lines.push(format!("  {} -> {} [label=\"{}\"];", id, won_id, self.copies[id]));
+            }
+        }
+        lines.push("}".to_owned());
+        lines.join("\n")
+    }
+
+    fn json(&self) -> String {
+        let cards: Vec<String> = self.copies.iter().map(|(id, copies)| {
+            let won: Vec<String> = self.won[id].iter().map(|id| id.to_string()).collect();
+            format!("{{\"id\": {}, \"copies\": {}, \"wins\": [{}]}}", id, copies, won.join(", "))
+        }).collect();
+        format!("{{\"total\": {}, \"cards\": [{}]}}", self.total(), cards.join(", "))
+    }
+
+    // the `n` cards that spawned the most copies, most first
+    fn top_contributors(&self, n: usize) -> String {
+        let mut ids: Vec<usize> = self.copies.keys().copied().collect();
+        ids.sort_by_key(|id| (std::cmp::Reverse(self.spawned(*id)), *id));
+        let total = self.total();
+        ids.iter().take(n).map(|id| format!(
+            "card {}: {} copies winning {} cards each spawned {} copies ({:.1}% of {})",
+            id, self.copies[id], self.won[id].len(), self.spawned(*id), 100.0 * self.spawned(*id) as f64 / total as f64, total
+        )).collect::<Vec<_>>().join("\n")
+    }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let input = fs::read_to_string("input/day4.txt")?;
     let result: Result<Vec<Card>, _> = input.lines().map(str::parse).collect();
-    let cards = result?;
 
-    let sum: u32 = cards.clone().iter().map(|c| c.score).sum();
+    let args: Vec<String> = std::env::args().collect();
+    let arg = |flag: &str| args.iter().skip_while(|arg| *arg != flag).nth(1);
+    let overflow = match arg("--overflow") {
+        Some(policy) => policy.parse()?,
+        None => Overflow::Error,
+    };
+    let scoring = match arg("--scoring") {
+        Some(scoring) => scoring.parse()?,
+        None => Scoring::Doubling,
+    };
+    let cascade = Cascade {
+        offset: arg("--offset").map(|n| n.parse()).transpose()?.unwrap_or(0),
+        cap: arg("--cap").map(|n| n.parse()).transpose()?,
+        overflow,
+    };
+    let matching = match arg("--matching") {
+        Some(matching) => matching.parse()?,
+        None => Matching::Multiset,
+    };
+
+    let cards: Vec<Card> = result?.into_iter().map(|c| c.with_matching(matching)).collect();
+    if args.iter().any(|arg| arg == "--strict") {
+        cards.iter().try_for_each(Card::validate)?;
+    }
+    let cards = index_cards(cards)?;
+
+    let sum = cards.values().try_fold(0u64, |sum, c| {
+        scoring.score(c.matches)
+            .and_then(|score| sum.checked_add(score))
+            .ok_or_else(|| format!("card {} takes the points past what fits in a u64", c.id))
+    })?;
     println!("{}", sum);
 
-    let total_cards: u32 = cards
-        .iter()
-        .map(|c: &Card| transitive_value_of_card(c, &cards))
-        .sum();
-    println!("{}", total_cards);
+    let tree = WinTree::new(&cards, &cascade)?;
+    match arg("--export").map(String::as_str) {
+        Some("dot") => println!("{}", tree.dot()),
+        Some("json") => println!("{}", tree.json()),
+        Some(format) => Err(format!("unknown export format '{}', expected dot or json", format))?,
+        None => {},
+    }
+    if let Some(n) = arg("--top") {
+        println!("{}", tree.top_contributors(n.parse()?));
+    }
+    println!("{}", tree.total());
     Ok(())
 }
 
@@ -96,6 +375,26 @@ fn main() -> Result<(), Box<dyn Error>> {
 mod tests_p1 {
     use crate::*;
 
+    fn total_cards(cards: &Cards, cascade: &Cascade) -> Result<u64, String> {
+        Ok(WinTree::new(cards, cascade)?.total())
+    }
+
+    // the naive recursion, exponential on long chains of wins
+    fn transitive_value_of_card(card: &Card, set: &[Card]) -> u32 {
+        1 + (card.id..card.id + card.matches)
+            .map(|c| transitive_value_of_card(&set[c], set))
+            .sum::<u32>()
+    }
+
+    // cards whose first `m` numbers match, for each `m` in `matches`
+    fn generate_cards(matches: &[usize]) -> Result<Vec<Card>, Box<dyn Error>> {
+        let winning = (1..=10).map(|n| n.to_string()).collect::<Vec<_>>();
+        Ok(matches.iter().enumerate().map(|(i, m)| {
+            let have = (1..=10).map(|n| if n <= *m { n } else { 50 + n }).map(|n| n.to_string()).collect::<Vec<_>>();
+            format!("Card {}: {} | {}", i + 1, winning.join(" "), have.join(" ")).parse()
+        }).collect::<Result<_, _>>()?)
+    }
+
     #[test]
     fn test_parse_card() -> Result<(), Box<dyn Error>> {
         let card_str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
@@ -106,7 +405,7 @@ mod tests_p1 {
             vec![83, 86, 6, 31, 17, 9, 48, 53],
         );
         assert_eq!(card, expected);
-        assert_eq!(card.score, 8);
+        assert_eq!(Scoring::Doubling.score(card.matches), Some(8));
         Ok(())
     }
 
@@ -132,7 +431,7 @@ mod tests_p1 {
             Card::new(6, vec!(31, 18, 13, 56, 72), vec!(74, 77, 10, 23, 35, 67, 36, 11))
         ];
         assert_eq!(cards, Ok(expected));
-        assert_eq!(cards?.iter().map(|c| c.score).sum::<u32>(), 13);
+        assert_eq!(cards?.iter().map(|c| Scoring::Doubling.score(c.matches)).sum::<Option<u64>>(), Some(13));
         Ok(())
     }
 
@@ -150,11 +449,136 @@ mod tests_p1 {
             .map(str::parse)
             .collect();
         let cards = result?;
-        let total_cards = cards
+        let total = cards
             .iter()
             .map(|c: &Card| transitive_value_of_card(c, &cards))
             .sum::<u32>();
-        assert_eq!(total_cards, 30);
+        assert_eq!(total, 30);
+        assert_eq!(total_cards(&index_cards(cards)?, &Cascade::from(Overflow::Error))?, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn test_total_cards_matches_recursion() -> Result<(), Box<dyn Error>> {
+        // a pseudo-random mix, trimmed so nobody wins cards past the end
+        let n = 40;
+        let matches: Vec<usize> = (0..n).map(|i| ((i * 7 + 3) % 5).min(n - 1 - i)).collect();
+        let cards = generate_cards(&matches)?;
+        let recursive: u32 = cards.iter().map(|c| transitive_value_of_card(c, &cards)).sum();
+        assert_eq!(total_cards(&index_cards(cards)?, &Cascade::from(Overflow::Error))?, recursive as u64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_total_cards_long_chain() -> Result<(), Box<dyn Error>> {
+        // every card wins the next, so card k ends up with k copies
+        let n: usize = 100_000;
+        let matches: Vec<usize> = (0..n).map(|i| if i + 1 < n { 1 } else { 0 }).collect();
+        let cards = generate_cards(&matches)?;
+        assert_eq!(total_cards(&index_cards(cards)?, &Cascade::from(Overflow::Error))?, (n * (n + 1) / 2) as u64);
+
+        // and here each card wins the ten after it, which the recursion
+        // would take far too long to count
+        let matches: Vec<usize> = (0..60).map(|i| 10.min(59 - i)).collect();
+        let cards = generate_cards(&matches)?;
+        assert_eq!(total_cards(&index_cards(cards)?, &Cascade::from(Overflow::Error))?, 1126092977910642170);
+
+        // the copies double with each card, so a few more are as far as a u64 goes
+        let chain = |n: usize| -> Result<u64, Box<dyn Error>> {
+            let matches: Vec<usize> = (0..n).map(|i| 10.min(n - 1 - i)).collect();
+            Ok(total_cards(&index_cards(generate_cards(&matches)?)?, &Cascade::from(Overflow::Error))?)
+        };
+        assert_eq!(chain(64)?, 17982150120482328570);
+        assert_eq!(chain(65).unwrap_err().to_string(), "the cards end up with too many copies between them");
+        assert_eq!(chain(66).unwrap_err().to_string(), "card 66 ends up with too many copies");
+        // clamped, the last cards keep winning themselves more copies
+        let matches = vec![10; 80];
+        let cards = index_cards(generate_cards(&matches)?)?;
+        assert!(WinTree::new(&cards, &Cascade::from(Overflow::Clamp)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow_policies() -> Result<(), Box<dyn Error>> {
+        // the last card wins two more, and the cards arrive out of order
+        let mut cards = generate_cards(&[1, 0, 2])?;
+        cards.swap(0, 2);
+        let table = index_cards(cards)?;
+        assert_eq!(total_cards(&table, &Cascade::from(Overflow::Clamp))?, 4);
+        assert_eq!(total_cards(&table, &Cascade::from(Overflow::Error)), Err("card 3 wins copies of card 4, but the last card is 3".to_owned()));
+        // card 3 wins 1 and 2, and card 1 wins the 2 again
+        assert_eq!(total_cards(&table, &Cascade::from(Overflow::Wrap))?, 7);
+
+        let table = index_cards(generate_cards(&[0, 0, 3])?)?;
+        assert_eq!(total_cards(&table, &Cascade::from(Overflow::Wrap)), Err("the wins wrap around into a loop, so the cards never run out".to_owned()));
+
+        let mut cards = generate_cards(&[0, 0, 0])?;
+        cards[2].id = 2;
+        assert_eq!(index_cards(cards.clone()).unwrap_err(), "card 2 appears more than once");
+        cards[2].id = 4;
+        assert_eq!(index_cards(cards).unwrap_err(), "card 3 is missing");
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeated_numbers() -> Result<(), Box<dyn Error>> {
+        let card: Card = "Card 7: 41 48 41 | 41 41 41 48 48 9".parse()?;
+        assert_eq!(card.matches, 3);
+        assert_eq!(card.clone().with_matching(Matching::Set).matches, 2);
+        assert_eq!(Scoring::Doubling.score(card.clone().with_matching(Matching::Set).matches), Some(2));
+        assert_eq!(card.validate(), Err("card 7: 41 appears more than once in the winning numbers".to_owned()));
+
+        let card: Card = "Card 8: 41 48 | 9 48 9".parse()?;
+        assert_eq!(card.validate(), Err("card 8: 9 appears more than once in the numbers you have".to_owned()));
+        assert_eq!("Card 9: 41 48 | 9 48".parse::<Card>()?.validate(), Ok(()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scoring_and_cascade_rules() -> Result<(), Box<dyn Error>> {
+        let scores = |scoring: &str| -> Result<Vec<u64>, String> {
+            let scoring: Scoring = scoring.parse()?;
+            (0..7).map(|m| scoring.score(m).ok_or("score too large".to_owned())).collect()
+        };
+        assert_eq!(scores("doubling")?, [0, 1, 2, 4, 8, 16, 32]);
+        assert_eq!(scores("linear")?, [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(scores("fibonacci")?, [0, 1, 1, 2, 3, 5, 8]);
+        assert_eq!(scores("table:0,1,3,7")?, [0, 1, 3, 7, 7, 7, 7]);
+        assert!(scores("table:0,x").is_err());
+        assert!(scores("squares").is_err());
+        // the biggest scores a u64 holds, and one match past them
+        assert_eq!(Scoring::Doubling.score(64), Some(1 << 63));
+        assert_eq!(Scoring::Doubling.score(65), None);
+        assert_eq!(Scoring::Fibonacci.score(93), Some(12200160415121876738));
+        assert_eq!(Scoring::Fibonacci.score(94), None);
+        assert_eq!(Scoring::Fibonacci.score(100), None);
+
+        // card 1 wins 2 and 3, and each of the two card 2s wins a 3
+        let table = index_cards(generate_cards(&[2, 1, 0, 0, 0])?)?;
+        assert_eq!(total_cards(&table, &Cascade::from(Overflow::Error))?, 1 + 2 + 4 + 1 + 1);
+        // skipping a card first, card 1 wins 3 and 4, and card 2 wins 4
+        assert_eq!(total_cards(&table, &Cascade { offset: 1, cap: None, overflow: Overflow::Error })?, 1 + 1 + 2 + 3 + 1);
+        // capped at one win each, card 1 only wins 2
+        assert_eq!(total_cards(&table, &Cascade { offset: 0, cap: Some(1), overflow: Overflow::Error })?, 1 + 2 + 3 + 1 + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_win_tree_export() -> Result<(), Box<dyn Error>> {
+        let tree = WinTree::new(&index_cards(generate_cards(&[2, 1, 0])?)?, &Cascade::from(Overflow::Error))?;
+        assert_eq!(tree.copies, BTreeMap::from([(1, 1), (2, 2), (3, 4)]));
+        assert_eq!(tree.dot(), [
+            "digraph cards {",
+            "  1 [label=\"Card 1\\n1 copies\"];",
+            "  2 [label=\"Card 2\\n2 copies\"];",
+            "  3 [label=\"Card 3\\n4 copies\"];",
+            "  1 -> 2 [label=\"1\"];",
+            "  1 -> 3 [label=\"1\"];",
+            "  2 -> 3 [label=\"2\"];",
+            "}",
+        ].join("\n"));
+        assert_eq!(tree.json(), r#"{"total": 7, "cards": [{"id": 1, "copies": 1, "wins": [2, 3]}, {"id": 2, "copies": 2, "wins": [3]}, {"id": 3, "copies": 4, "wins": []}]}"#);
+        assert_eq!(tree.top_contributors(2), "card 1: 1 copies winning 2 cards each spawned 2 copies (28.6% of 7)\ncard 2: 2 copies winning 1 cards each spawned 2 copies (28.6% of 7)");
         Ok(())
     }
 }
@@ -1,40 +1,495 @@
 use std::error::Error;
 use std::fs;
+use std::str::FromStr;
 
 use core::iter::Iterator;
 
 use itertools::Itertools;
+use num_bigint::BigUint;
 
-fn parse_input(lines: &mut dyn Iterator<Item=&str>) -> Result<Vec<(u64, u64)>, Box<dyn Error>> {
-    let times: Result<Vec<u64>, _> = lines.next().to_owned().unwrap().split_whitespace().skip(1).map(|u| u64::from_str_radix(u, 10)).collect();
-    let dist: Result<Vec<u64>, _> = lines.next().to_owned().unwrap().split_whitespace().skip(1).map(|u| u64::from_str_radix(u, 10)).collect();
-    Ok(times?.into_iter().zip(dist?.into_iter()).collect())
-    // Ok(vec![])
+// None once a number is too big for the u128 solvers
+fn to_u128(n: &BigUint) -> Option<u128> {
+    u128::try_from(n).ok()
+}
+
+type Race = (BigUint, BigUint);
+
+/// How to read the columns of the sheet: as separate races, or as the digits
+/// of one long race that were just badly kerned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kerning {
+    Separate,
+    Kerned,
+}
+
+impl FromStr for Kerning {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "separate" => Ok(Kerning::Separate),
+            "kerned" => Ok(Kerning::Kerned),
+            _ => Err(format!("unknown kerning '{}', expected separate or kerned", s)),
+        }
+    }
+}
+
+// Time:      7  15   30
+fn parse_row<'a>(line_no: usize, line: Option<&'a str>, header: &str) -> Result<Vec<&'a str>, String> {
+    let mut tokens = line.ok_or_else(|| format!("line {}: missing {} line", line_no, header))?.split_whitespace();
+    match tokens.next() {
+        Some(token) if token == header => {},
+        Some(token) => return Err(format!("line {}: expected '{}', found '{}'", line_no, header, token)),
+        None => return Err(format!("line {}: missing {} line", line_no, header)),
+    }
+    let columns = tokens.collect_vec();
+    if columns.is_empty() {
+        return Err(format!("line {}: no columns after '{}'", line_no, header));
+    }
+    if let Some(column) = columns.iter().find(|c| !c.chars().all(|c| c.is_ascii_digit())) {
+        return Err(format!("line {}: '{}' is not a number", line_no, column));
+    }
+    Ok(columns)
+}
+
+fn parse_input(input: &str, kerning: Kerning) -> Result<Vec<Race>, Box<dyn Error>> {
+    let mut lines = input.trim_end().lines();
+    let times = parse_row(1, lines.next(), "Time:")?;
+    let dists = parse_row(2, lines.next(), "Distance:")?;
+    if let Some(line) = lines.next() {
+        Err(format!("line 3: unexpected input after the distances: '{}'", line))?
+    }
+    if times.len() != dists.len() {
+        Err(format!("line 2: {} distances for {} times", dists.len(), times.len()))?
+    }
+    let columns = match kerning {
+        Kerning::Separate => times.into_iter().map(str::to_owned).zip(dists.into_iter().map(str::to_owned)).collect_vec(),
+        Kerning::Kerned => vec![(times.concat(), dists.concat())],
+    };
+    columns.into_iter().map(|(t, d)| Ok((t.parse()?, d.parse()?))).collect()
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let input = fs::read_to_string("input/day6.txt")?;
-    let mut lines = input.lines();
-    // println!("{:?}", parse_input_1(&mut lines)?);
-    println!("{}", solution1(parse_input(&mut lines.clone())?));
-    println!("{}", solution2(parse_input(&mut lines)?)?);
+    let args = std::env::args().collect_vec();
+    let arg = |flag: &str| args.iter().skip_while(|arg| *arg != flag).nth(1);
+    let kernings = match arg("--kerning") {
+        Some(kerning) => vec![kerning.parse()?],
+        None => vec![Kerning::Separate, Kerning::Kerned],
+    };
+    for kerning in kernings {
+        let races = parse_input(&input, kerning)?;
+        match (arg("--model"), arg("--report")) {
+            (Some(_), Some(_)) => Err("--report only describes the classic boat, so it can't be combined with --model")?,
+            (Some(model), None) => println!("{}", model_solution(&races, parse_model(model)?.as_ref())?),
+            (None, Some(format)) if format == "text" => println!("{}", races.iter().map(|(t, d)| RaceReport::new(t, d).text()).join("\n")),
+            (None, Some(format)) if format == "json" => println!("[{}]", races.iter().map(|(t, d)| RaceReport::new(t, d).json()).join(", ")),
+            (None, Some(format)) => Err(format!("unknown report format '{}', expected text or json", format))?,
+            (None, None) => println!("{}", solution(&races)),
+        }
+    }
     Ok(())
 }
 
-fn solution1(times_and_distances: Vec<(u64, u64)>) -> u64 {
-    let winning_ways = times_and_distances.into_iter().map(|(t,d)| 
-        (0..t).filter(|x| x * (t-x) > d).count()
-    );
-    println!("{:?}", winning_ways.clone().collect_vec());
-    winning_ways.product::<usize>() as u64
+/// How far a boat goes in a race of `time` ms when its button is held for
+/// `hold` ms, or None if that overflows. As the hold grows the distance has
+/// to rise strictly to a peak (which may be flat) and then never rise again,
+/// because that's what lets `count_winning_holds` binary search it.
+trait Motion {
+    fn distance(&self, hold: u128, time: u128) -> Option<u128>;
+
+    // the number of holds that beat the record, for models with a formula for it
+    fn closed_form(&self, _time: u128, _record: u128) -> Option<u128> {
+        None
+    }
+}
+
+/// Every ms held adds `acceleration` to a starting speed of `start_speed`.
+/// The puzzle's boat accelerates by 1 from a standstill.
+struct Accelerating {
+    acceleration: u128,
+    start_speed: u128,
+}
+
+impl Motion for Accelerating {
+    fn distance(&self, hold: u128, time: u128) -> Option<u128> {
+        self.acceleration.checked_mul(hold)?.checked_add(self.start_speed)?.checked_mul(time - hold)
+    }
+
+    // (s + a h) (t - h) = d at h = (a t - s ± sqrt((a t + s)^2 - 4 a d)) / 2a;
+    // as in `winning_ways`, the integer roots are nudged onto the boundaries
+    fn closed_form(&self, time: u128, record: u128) -> Option<u128> {
+        let (a, s) = (self.acceleration, self.start_speed);
+        if a == 0 {
+            return None;
+        }
+        // the distance never exceeds (a t + s)^2, so once this fits nothing overflows
+        let top_speed = a.checked_mul(time)?.checked_add(s)?;
+        let discriminant = match top_speed.checked_mul(top_speed)?.checked_sub(a.checked_mul(4)?.checked_mul(record)?) {
+            Some(discriminant) => i128::try_from(discriminant).ok()?,
+            None => return Some(0),
+        };
+        let wins = |h: u128| self.distance(h, time).is_some_and(|x| x > record);
+        let root = discriminant.isqrt();
+        let vertex = i128::try_from(a * time).ok()? - i128::try_from(s).ok()?;
+        let two_a = i128::try_from(a.checked_mul(2)?).ok()?;
+        let guess = |numerator: i128| numerator.div_euclid(two_a).clamp(0, time as i128) as u128;
+
+        let (mut lo, mut hi) = (guess(vertex - root), guess(vertex + root));
+        while lo <= hi && !wins(lo) {
+            lo += 1;
+        }
+        while lo > 0 && wins(lo - 1) {
+            lo -= 1;
+        }
+        while hi >= lo && hi > 0 && !wins(hi) {
+            hi -= 1;
+        }
+        while hi < time && wins(hi + 1) {
+            hi += 1;
+        }
+        Some(if lo <= hi && wins(lo) { hi - lo + 1 } else { 0 })
+    }
+}
+
+/// Accelerates like the puzzle's boat, but can't go faster than `cap`.
+struct Capped {
+    acceleration: u128,
+    cap: u128,
+}
+
+impl Motion for Capped {
+    fn distance(&self, hold: u128, time: u128) -> Option<u128> {
+        self.acceleration.checked_mul(hold)?.min(self.cap).checked_mul(time - hold)
+    }
+}
+
+/// Accelerates like the puzzle's boat, but once the button is released it
+/// loses `deceleration` of its speed every ms, so it may coast to a stop
+/// before the race is over.
+struct Drag {
+    acceleration: u128,
+    deceleration: u128,
+}
+
+impl Motion for Drag {
+    fn distance(&self, hold: u128, time: u128) -> Option<u128> {
+        let speed = self.acceleration.checked_mul(hold)?;
+        let moving = if self.deceleration == 0 {
+            time - hold
+        } else {
+            speed.div_ceil(self.deceleration).min(time - hold)
+        };
+        // speed, speed - deceleration, ... for as long as it's moving
+        let lost = self.deceleration.checked_mul(moving.checked_mul(moving.saturating_sub(1))? / 2)?;
+        Some(moving.checked_mul(speed)? - lost)
+    }
+}
+
+// binary search for the peak, then for the edges of the winning interval on
+// either side of it
+fn count_winning_holds(model: &dyn Motion, time: u128, record: u128) -> Option<u128> {
+    if let Some(ways) = model.closed_form(time, record) {
+        return Some(ways);
+    }
+    let distance = |hold: u128| model.distance(hold, time);
+
+    let (mut lo, mut hi) = (0, time);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if distance(mid)? >= distance(mid + 1)? { hi = mid } else { lo = mid + 1 }
+    }
+    let peak = lo;
+    if distance(peak)? <= record {
+        return Some(0);
+    }
+
+    // first winner at or before the peak
+    let (mut lo, mut hi) = (0, peak);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if distance(mid)? > record { hi = mid } else { lo = mid + 1 }
+    }
+    let first = lo;
+
+    // last winner at or after it
+    let (mut lo, mut hi) = (peak, time);
+    while lo < hi {
+        let mid = hi - (hi - lo) / 2;
+        if distance(mid)? > record { lo = mid } else { hi = mid - 1 }
+    }
+    Some(lo - first + 1)
+}
+
+// classic, accelerate:A[:START], capped:A:CAP or drag:A:DECELERATION
+fn parse_model(s: &str) -> Result<Box<dyn Motion>, Box<dyn Error>> {
+    let parts = s.split(':').collect_vec();
+    let number = |i: usize| -> Result<u128, Box<dyn Error>> {
+        Ok(parts.get(i).ok_or_else(|| format!("model '{}' is missing a parameter", s))?.parse()?)
+    };
+    Ok(match parts[0] {
+        "classic" => Box::new(Accelerating { acceleration: 1, start_speed: 0 }),
+        "accelerate" => Box::new(Accelerating { acceleration: number(1)?, start_speed: if parts.len() > 2 { number(2)? } else { 0 } }),
+        "capped" => Box::new(Capped { acceleration: number(1)?, cap: number(2)? }),
+        "drag" => Box::new(Drag { acceleration: number(1)?, deceleration: number(2)? }),
+        _ => Err(format!("unknown model '{}'", s))?,
+    })
+}
+
+// the product of the ways to win each race under the given model
+fn model_solution(races: &[Race], model: &dyn Motion) -> Result<u128, Box<dyn Error>> {
+    races.iter().try_fold(1u128, |product, (t, d)| {
+        let overflow = || format!("race {} / {} overflows a u128", t, d);
+        let ways = count_winning_holds(model, to_u128(t).ok_or_else(overflow)?, to_u128(d).ok_or_else(overflow)?).ok_or_else(overflow)?;
+        Ok(product.checked_mul(ways).ok_or("the product of the ways to win overflows a u128")?)
+    })
+}
+
+// x * (t - x) > d is symmetric about t / 2, so the winners are [lo, t - lo].
+// The isqrt of the discriminant puts lo within a step of the real root, and
+// nudging it onto the exact boundary means exact roots (ties) never count.
+// None if any step would overflow.
+fn winning_ways(t: u128, d: u128) -> Option<u128> {
+    let wins = |x: u128| x * (t - x) > d;
+    let discriminant = match (t.checked_mul(t)?).checked_sub(d.checked_mul(4)?) {
+        Some(discriminant) => discriminant,
+        None => return Some(0),
+    };
+    let mut lo = (t - discriminant.isqrt()) / 2;
+    while lo <= t / 2 && !wins(lo) {
+        lo += 1;
+    }
+    while lo > 0 && wins(lo - 1) {
+        lo -= 1;
+    }
+    if lo > t / 2 {
+        Some(0)
+    } else {
+        Some(t - 2 * lo + 1)
+    }
+}
+
+// the same interval, binary searching for lo over the rising half of the
+// curve, for races too long for the closed form to fit in a u128
+fn big_winning_ways(t: &BigUint, d: &BigUint) -> BigUint {
+    let wins = |x: &BigUint| &(x * &(t - x)) > d;
+    let mid = t >> 1;
+    if !wins(&mid) {
+        return BigUint::ZERO;
+    }
+    let (mut lo, mut hi) = (BigUint::ZERO, mid);
+    let one = BigUint::from(1u32);
+    while lo < hi {
+        let x = (&lo + &hi) >> 1;
+        if wins(&x) {
+            hi = x;
+        } else {
+            lo = &x + &one;
+        }
+    }
+    &(t - &(&lo + &lo)) + &one
+}
+
+// small races take the closed form, anything bigger the arbitrary precision path
+fn count_wins(t: &BigUint, d: &BigUint) -> BigUint {
+    match (to_u128(t), to_u128(d)) {
+        (Some(t), Some(d)) => winning_ways(t, d).map(BigUint::from),
+        _ => None,
+    }.unwrap_or_else(|| big_winning_ways(t, d))
+}
+
+/// Everything about how to win one race with the puzzle's boat. The winning
+/// holds are symmetric about the middle of the race, so they all follow from
+/// the number of ways to win.
+#[derive(Debug, PartialEq)]
+struct RaceReport {
+    time: BigUint,
+    record: BigUint,
+    ways: BigUint,
+    // the first and last winning holds
+    interval: Option<(BigUint, BigUint)>,
+    // one hold for even times, two for odd
+    optimal: Vec<BigUint>,
+    max_distance: BigUint,
+}
+
+impl RaceReport {
+    fn new(time: &BigUint, record: &BigUint) -> RaceReport {
+        let one = BigUint::from(1u32);
+        let ways = count_wins(time, record);
+        let interval = (ways != BigUint::ZERO).then(|| {
+            let lo = (&(time + &one) - &ways) >> 1;
+            let hi = time - &lo;
+            (lo, hi)
+        });
+        let optimal = [time >> 1, (time + &one) >> 1].into_iter().dedup().collect_vec();
+        let max_distance = &optimal[0] * &(time - &optimal[0]);
+        RaceReport { time: time.clone(), record: record.clone(), ways, interval, optimal, max_distance }
+    }
+
+    // how far the best hold beats (or falls short of) the record
+    fn margin(&self) -> String {
+        if self.max_distance >= self.record {
+            (&self.max_distance - &self.record).to_string()
+        } else {
+            format!("-{}", &self.record - &self.max_distance)
+        }
+    }
 
+    fn text(&self) -> String {
+        let interval = match &self.interval {
+            Some((lo, hi)) => format!("holds [{}, {}] win", lo, hi),
+            None => "no hold wins".to_owned(),
+        };
+        format!(
+            "time {} record {}: {} ways, {}, best hold {} goes {} (margin {})",
+            self.time, self.record, self.ways, interval, self.optimal.iter().join(" or "), self.max_distance, self.margin()
+        )
+    }
+
+    fn json(&self) -> String {
+        let interval = match &self.interval {
+            Some((lo, hi)) => format!("[{}, {}]", lo, hi),
+            None => "null".to_owned(),
+        };
+        format!(
+            "{{\"time\": {}, \"record\": {}, \"ways\": {}, \"interval\": {}, \"optimal\": [{}], \"max_distance\": {}, \"margin\": {}}}",
+            self.time, self.record, self.ways, interval, self.optimal.iter().join(", "), self.max_distance, self.margin()
+        )
+    }
+}
+
+// the product of the ways to win each race
+fn solution(races: &[Race]) -> BigUint {
+    races.iter().map(|(t, d)| count_wins(t, d)).fold(BigUint::from(1u32), |product, ways| &product * &ways)
 }
 
-fn solution2(times_and_distances: Vec<(u64, u64)>) -> Result<u64, Box<dyn Error>> {
-    let times = u64::from_str_radix(&times_and_distances.iter().map(|x| x.0.to_string()).join(""), 10)?; 
-    let dists = u64::from_str_radix(&times_and_distances.iter().map(|x| x.1.to_string()).join(""), 10)?; 
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn brute_force(t: u128, d: u128) -> u128 {
+        (0..=t).filter(|x| x * (t - x) > d).count() as u128
+    }
+
+    #[test]
+    fn test_sample() -> Result<(), Box<dyn Error>> {
+        let sample = "Time:      7  15   30\nDistance:  9  40  200";
+        let races = parse_input(sample, Kerning::Separate)?;
+        assert_eq!(races.iter().map(|(t, d)| count_wins(t, d).to_string()).collect_vec(), ["4", "8", "9"]);
+        assert_eq!(solution(&races), BigUint::from(288u32));
+        let race = parse_input(sample, Kerning::Kerned)?;
+        assert_eq!(race, [(BigUint::from(71530u32), BigUint::from(940200u32))]);
+        assert_eq!(solution(&race), BigUint::from(71503u32));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |s: &str| parse_input(s, Kerning::Separate).unwrap_err().to_string();
+        assert_eq!(err("Time: 7 15\nDistance: 9"), "line 2: 1 distances for 2 times");
+        assert_eq!(err("Times: 7\nDistance: 9"), "line 1: expected 'Time:', found 'Times:'");
+        assert_eq!(err("Time: 7\n"), "line 2: missing Distance: line");
+        assert_eq!(err("Time: 7\nDistance: 9x"), "line 2: '9x' is not a number");
+        assert_eq!(err("Time:\nDistance: 9"), "line 1: no columns after 'Time:'");
+        assert_eq!(err("Time: 7\nDistance: 9\nTime: 8"), "line 3: unexpected input after the distances: 'Time: 8'");
+        assert_eq!("wide".parse::<Kerning>(), Err("unknown kerning 'wide', expected separate or kerned".to_owned()));
+    }
+
+    #[test]
+    fn test_exact_roots() {
+        // 10 * 20 = 200 only ties the record, so 10 and 20 don't count
+        assert_eq!(winning_ways(30, 200), Some(9));
+        // the only way to reach 4 in 4ms is to hold for 2, which only ties
+        assert_eq!(winning_ways(4, 4), Some(0));
+        assert_eq!(winning_ways(4, 3), Some(1));
+        assert_eq!(winning_ways(5, 6), Some(0));
+        assert_eq!(winning_ways(0, 0), Some(0));
+        assert_eq!(winning_ways(1, 0), Some(0));
+        assert_eq!(winning_ways(2, 0), Some(1));
+        assert_eq!(winning_ways(10, 100), Some(0));
+
+        for t in 0..60 {
+            for d in 0..=(t * t / 4 + 1) {
+                assert_eq!(winning_ways(t, d), Some(brute_force(t, d)), "t={} d={}", t, d);
+                assert_eq!(big_winning_ways(&BigUint::from(t), &BigUint::from(d)), BigUint::from(brute_force(t, d)), "t={} d={}", t, d);
+            }
+        }
+    }
+
+    #[test]
+    fn test_huge_races() -> Result<(), Box<dyn Error>> {
+        // t * t overflows a u128, so this has to take the big path
+        let t = u128::MAX / 3;
+        assert_eq!(winning_ways(t, 0), None);
+        assert_eq!(count_wins(&BigUint::from(t), &BigUint::from(0u32)), BigUint::from(t - 1));
+
+        // the best hold for t = 10^60 is t / 2, which only ties a record of (t / 2)^2
+        let t: BigUint = format!("1{}", "0".repeat(60)).parse()?;
+        let d: BigUint = format!("25{}", "0".repeat(118)).parse()?;
+        assert_eq!(count_wins(&t, &d), BigUint::from(0u32));
+        assert_eq!(count_wins(&t, &(&d - &BigUint::from(1u32))), BigUint::from(1u32));
+
+        // just under the tie, that one hold wins by 1
+        let half = format!("5{}", "0".repeat(59));
+        let report = RaceReport::new(&t, &(&d - &BigUint::from(1u32)));
+        assert_eq!(report.interval, Some((half.parse()?, half.parse()?)));
+        assert_eq!(report.text(), format!("time {} record {}: 1 ways, holds [{}, {}] win, best hold {} goes {} (margin 1)", t, &d - &BigUint::from(1u32), half, half, half, d));
+        Ok(())
+    }
+
+    // the models' distances by brute force
+    fn brute_force_model(model: &dyn Motion, t: u128, d: u128) -> u128 {
+        (0..=t).filter(|h| model.distance(*h, t).unwrap() > d).count() as u128
+    }
+
+    #[test]
+    fn test_models() -> Result<(), Box<dyn Error>> {
+        let races = parse_input("Time:      7  15   30\nDistance:  9  40  200", Kerning::Separate)?;
+        assert_eq!(model_solution(&races, parse_model("classic")?.as_ref())?, 288);
+
+        let models: Vec<Box<dyn Motion>> = vec![
+            Box::new(Accelerating { acceleration: 1, start_speed: 0 }),
+            Box::new(Accelerating { acceleration: 3, start_speed: 0 }),
+            Box::new(Accelerating { acceleration: 2, start_speed: 5 }),
+            Box::new(Accelerating { acceleration: 1, start_speed: 40 }),
+            Box::new(Capped { acceleration: 2, cap: 9 }),
+            Box::new(Capped { acceleration: 1, cap: 100 }),
+            Box::new(Drag { acceleration: 1, deceleration: 1 }),
+            Box::new(Drag { acceleration: 5, deceleration: 2 }),
+            Box::new(Drag { acceleration: 1, deceleration: 0 }),
+        ];
+        for model in models.iter() {
+            for t in 0..40 {
+                let max = (0..=t).map(|h| model.distance(h, t).unwrap()).max().unwrap();
+                for d in 0..=max + 1 {
+                    assert_eq!(count_winning_holds(model.as_ref(), t, d), Some(brute_force_model(model.as_ref(), t, d)), "t={} d={}", t, d);
+                }
+            }
+        }
+
+        // with a head start of 40, holding at all costs more than it gains in a 30ms race
+        assert_eq!(count_winning_holds(&Accelerating { acceleration: 1, start_speed: 40 }, 30, 1199), Some(1));
+        // released at 6 (speed 6) it covers 6 + 5 + 4 + 3 + 2 + 1 and stops
+        assert_eq!(Drag { acceleration: 1, deceleration: 1 }.distance(6, 20), Some(21));
+        assert!(parse_model("drag:1").is_err());
+        assert!(parse_model("warp:9").is_err());
+        Ok(())
+    }
 
-    let winning_ways =(0..times).filter(|x| x * (times-x) > dists).count();
+    #[test]
+    fn test_report() -> Result<(), Box<dyn Error>> {
+        let races = parse_input("Time:      7  15   30\nDistance:  9  40  200", Kerning::Separate)?;
+        let reports = races.iter().map(|(t, d)| RaceReport::new(t, d)).collect_vec();
+        assert_eq!(reports[0].interval, Some((BigUint::from(2u32), BigUint::from(5u32))));
+        assert_eq!(reports[0].optimal, [BigUint::from(3u32), BigUint::from(4u32)]);
+        assert_eq!(reports[2].interval, Some((BigUint::from(11u32), BigUint::from(19u32))));
+        assert_eq!(reports[1].text(), "time 15 record 40: 8 ways, holds [4, 11] win, best hold 7 or 8 goes 56 (margin 16)");
+        assert_eq!(reports[2].json(), r#"{"time": 30, "record": 200, "ways": 9, "interval": [11, 19], "optimal": [15], "max_distance": 225, "margin": 25}"#);
 
-    Ok(winning_ways as u64)
+        let tie = RaceReport::new(&BigUint::from(4u32), &BigUint::from(4u32));
+        assert_eq!(tie.text(), "time 4 record 4: 0 ways, no hold wins, best hold 2 goes 4 (margin 0)");
+        assert_eq!(RaceReport::new(&BigUint::from(4u32), &BigUint::from(7u32)).margin(), "-3");
+        Ok(())
+    }
 }
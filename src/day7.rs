@@ -1,9 +1,161 @@
-use std::{error::Error, fs, cmp::Ordering};
+use std::{error::Error, fs, cmp::Ordering, str::FromStr};
 use std::fmt::Debug;
 use std::fmt;
 use itertools::Itertools;
 
-#[derive(Clone, Eq, PartialEq, PartialOrd)] 
+/// Everything that differs between the variants of Camel Cards: the order of
+/// the cards from weakest to strongest, which cards (if any) are wild, and the
+/// value a wildcard has when two hands of the same type are compared.
+#[derive(Clone, Debug)]
+struct Ruleset {
+    order: Vec<char>,
+    wildcards: Vec<char>,
+    wildcard_value: u8,
+}
+
+impl Ruleset {
+    fn new(order: &str, wildcards: &str, wildcard_value: u8) -> Ruleset {
+        Ruleset { order: order.chars().collect(), wildcards: wildcards.chars().collect(), wildcard_value }
+    }
+
+    fn standard() -> Ruleset {
+        Ruleset::new("23456789TJQKA", "", 0)
+    }
+
+    // J cards are jokers, and the weakest individual cards
+    fn jokers() -> Ruleset {
+        Ruleset::new("23456789TJQKA", "J", 1)
+    }
+
+    fn is_wildcard(&self, card: char) -> bool {
+        self.wildcards.contains(&card)
+    }
+
+    // every card a hand under these rules may hold
+    fn alphabet(&self) -> Vec<char> {
+        self.order.iter().chain(self.wildcards.iter()).copied().unique().collect()
+    }
+
+    fn check(&self, hand: &Hand) -> Result<(), String> {
+        let alphabet = self.alphabet();
+        match hand.cards.chars().find_position(|c| !alphabet.contains(c)) {
+            Some((pos, card)) => Err(format!("invalid card '{}' at position {}", card, pos + 1)),
+            None => Ok(()),
+        }
+    }
+
+    // the weakest non-wild card is worth 2, so the wildcard value can sit below it
+    fn card_value(&self, card: char) -> Result<u8, String> {
+        if self.is_wildcard(card) {
+            Ok(self.wildcard_value)
+        } else {
+            match self.order.iter().position(|c| *c == card) {
+                Some(p) => Ok(p as u8 + 2),
+                None => Err(format!("card '{}' is not part of this ruleset", card)),
+            }
+        }
+    }
+
+    // every wildcard joins the biggest group, which is always the best use of it
+    fn typ(&self, hand: &Hand) -> HandType {
+        let jokers = hand.cards.chars().filter(|c| self.is_wildcard(*c)).count();
+
+        let mut groups = hand.cards.chars()
+            .filter(|c| !self.is_wildcard(*c))
+            .counts()
+            .into_values()
+            .sorted()
+            .rev()
+            .collect_vec();
+
+        match groups.first_mut() {
+            Some(biggest) => *biggest += jokers,
+            None => groups.push(jokers),
+        }
+        HandType(groups)
+    }
+
+    // the concrete hand the wildcards stand in for: they all become copies of
+    // the most common (then strongest) other card, or of the strongest card
+    // in the order if the hand is nothing but wildcards
+    fn resolve(&self, hand: &Hand) -> Result<(String, HandType), String> {
+        let best = hand.cards.chars()
+            .filter(|c| !self.is_wildcard(*c))
+            .counts()
+            .into_iter()
+            .map(|(c, n)| Ok((n, self.card_value(c)?, c)))
+            .collect::<Result<Vec<_>, String>>()?
+            .into_iter()
+            .max()
+            .map(|(_, _, c)| c)
+            .or_else(|| self.order.iter().rev().copied().find(|c| !self.is_wildcard(*c)));
+        let resolved = match best {
+            Some(best) => hand.cards.chars().map(|c| if self.is_wildcard(c) { best } else { c }).collect(),
+            None => hand.cards.clone(),
+        };
+        Ok((resolved, self.typ(hand)))
+    }
+
+    // cards outside the ruleset, which `read_input` turns away, rank below all of its own
+    fn cmp_first_card(&self, left: &str, right: &str) -> Ordering {
+        let values = |cards: &str| cards.chars().map(|c| self.card_value(c).ok()).collect_vec();
+        values(left).cmp(&values(right))
+    }
+
+    // hands with the same cards are Equal, whatever their bids
+    fn cmp(&self, left: &Hand, right: &Hand) -> Ordering {
+        self.typ(left).cmp(&self.typ(right))
+            .then_with(|| self.cmp_first_card(&left.cards, &right.cards))
+    }
+
+    // the cards themselves only decide between hands with cards outside the ruleset
+    fn cmp_with_bids(&self, left: &Hand, right: &Hand) -> Ordering {
+        self.cmp(left, right)
+            .then_with(|| left.points.cmp(&right.points))
+            .then_with(|| left.cards.cmp(&right.cards))
+    }
+
+    // comparing keys of hands of the same length is the same as `cmp`, but
+    // the typing only has to happen once per hand
+    fn key(&self, hand: &Hand) -> Result<Key, String> {
+        let len = hand.cards.chars().count();
+        let typ = self.typ(hand);
+        let values = hand.cards.chars().map(|c| self.card_value(c)).collect::<Result<Vec<_>, _>>()?;
+        if len > MAX_PACKED_LEN {
+            return Ok(Key::Unpacked(typ, values));
+        }
+        let packed = typ.0.iter().pad_using(len, |_| &0).fold(0, |key, n| key << 4 | *n as u128);
+        Ok(Key::Packed(values.into_iter().fold(packed, |key, value| key << 8 | value as u128)))
+    }
+
+    fn ranked<'a>(&self, hands: &'a [Hand]) -> Result<Vec<&'a Hand>, String> {
+        rank_by(hands, |hand| self.key(hand).map(|key| (key, hand.points)))
+    }
+}
+
+/// A hand's place in a ruleset's order. Hands up to `MAX_PACKED_LEN` cards
+/// pack their type's group sizes as a nibble each (padded out to one per
+/// card) and then a byte per card into one u128; longer hands keep the type
+/// and card values as they are.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Packed(u128),
+    Unpacked(HandType, Vec<u8>),
+}
+
+// weakest first, computing each hand's key only once
+fn rank_by<H, K: Ord, E>(hands: &[H], key: impl Fn(&H) -> Result<K, E>) -> Result<Vec<&H>, E> {
+    let mut keyed = hands.iter().map(|hand| Ok((key(hand)?, hand))).collect::<Result<Vec<_>, E>>()?;
+    keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(keyed.into_iter().map(|(_, hand)| hand).collect())
+}
+
+// the bids of the hands, weakest first
+fn total_winnings(bids: impl Iterator<Item = u64>) -> u64 {
+    bids.enumerate().map(|(rank, bid)| (rank+1) as u64 * bid).sum::<u64>()
+}
+
+#[derive(Clone, Eq, PartialEq)]
 struct Hand {
     cards: String,
     points: u64
@@ -13,151 +165,273 @@ impl Debug for Hand {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_tuple("Hand")
          .field(&self.cards)
-         .field(&self.typ_with_wildcard('J'))
+         .field(&Ruleset::jokers().typ(self))
          .field(&self.points)
          .finish()
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum HandType {
+/// The sizes of the groups of matching cards in a hand, biggest first, so
+/// `[3, 2]` is a full house. Comparing these lexicographically ranks hands of
+/// any length the way Camel Cards ranks five card ones.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+struct HandType(Vec<usize>);
+
+impl fmt::Display for HandType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let numbers = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"];
+        let groups = self.0.iter().filter(|n| **n > 1).collect_vec();
+        // past ten there are no names, only the group sizes
+        match groups.as_slice() {
+            [] => write!(f, "high card"),
+            [3, 2] => write!(f, "full house"),
+            [3, 3] => write!(f, "double triple"),
+            [n] if **n > 2 && **n < numbers.len() => write!(f, "{} of a kind", numbers[**n]),
+            pairs if pairs.iter().all(|n| **n == 2) && pairs.len() < numbers.len() => write!(f, "{} pair", numbers[pairs.len()]),
+            _ => write!(f, "{}", self.0.iter().join("-")),
+        }
+    }
+}
+
+// Eq compares the bids too, so they have to break ties here as well
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        Ruleset::standard().cmp_with_bids(self, other)
+    }
+}
+
+impl PartialOrd for Hand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum PokerType {
     HighCard,
     OnePair,
     TwoPair,
     ThreeOfAKind,
+    Straight,
+    Flush,
     FullHouse,
     FourOfAKind,
-    FiveOfAKind,
+    StraightFlush,
 }
 
-impl Hand {
+/// A hand of ordinary poker: five cards written as rank then suit, like `AS`
+/// or `TD`. Unlike Camel Cards, hands of the same type are decided by their
+/// kickers rather than by the order the cards were dealt in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct PokerHand {
+    cards: Vec<(char, char)>,
+    points: u64,
+}
 
-    fn cmp_with_jokers(&self, other: &Hand) -> Ordering {
+impl PokerHand {
+    // the type, and the card values that break ties between hands of that
+    // type, most significant first
+    fn evaluate(&self) -> Result<(PokerType, Vec<u8>), String> {
+        use PokerType::*;
 
-        let c = if self.typ_with_wildcard('J') > other.typ_with_wildcard('J') {
-            Ordering::Greater
-        } else if self.typ_with_wildcard('J') < other.typ_with_wildcard('J') {
-            Ordering::Less
-        } else {
-            Hand::cmp_first_card_with_jokers(&self.cards, &other.cards)
+        let standard = Ruleset::standard();
+        let ranks: String = self.cards.iter().map(|(rank, _)| *rank).collect();
+        let HandType(groups) = standard.typ(&Hand { cards: ranks, points: self.points });
+
+        // biggest group first, so the three of a full house outranks its pair
+        let kickers = self.cards.iter()
+            .map(|(rank, _)| standard.card_value(*rank))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .counts()
+            .into_iter()
+            .sorted_by_key(|(value, n)| (*n, *value))
+            .rev()
+            .map(|(value, _)| value)
+            .collect_vec();
+
+        let flush = self.cards.iter().map(|(_, suit)| suit).all_equal();
+        // an ace can also play low, below the 2
+        let straight = match kickers.as_slice() {
+            [14, 5, 4, 3, 2] => Some(5),
+            [high, .., low] if kickers.len() == 5 && high - low == 4 => Some(*high),
+            _ => None,
+        };
+
+        let typ = match (straight, flush, groups.as_slice()) {
+            (Some(_), true, _) => StraightFlush,
+            (_, _, [4, ..]) => FourOfAKind,
+            (_, _, [3, 2]) => FullHouse,
+            (_, true, _) => Flush,
+            (Some(_), _, _) => Straight,
+            (_, _, [3, ..]) => ThreeOfAKind,
+            (_, _, [2, 2, ..]) => TwoPair,
+            (_, _, [2, ..]) => OnePair,
+            _ => HighCard,
         };
-        c
+        Ok((typ, straight.map(|high| vec![high]).unwrap_or(kickers)))
     }
 
-    fn cmp_first_card(left: &String, right: &String) -> Ordering {
-        let cmps = left.chars().zip(right.chars()).map(|(c1, c2)| Hand::card_value(c1).cmp(&Hand::card_value(c2)));
-        cmps.filter(|c| *c != Ordering::Equal).nth(0).unwrap()
+    // the type, then a nibble per kicker
+    fn key(&self) -> Result<u64, String> {
+        let (typ, kickers) = self.evaluate()?;
+        Ok(kickers.iter().pad_using(5, |_| &0).fold(typ as u64, |key, value| key << 4 | *value as u64))
     }
+}
 
-    fn card_value_with_jokers(card: char) -> u8 {
-        if card.is_numeric() {
-            u8::from_str_radix(&card.to_string(), 10).unwrap()
-        } else {
-            match card {
-                'A' => 14,
-                'K' => 13,
-                'Q' => 12,
-                'J' => 1,
-                'T' => 10,
-                _ => panic!(),
-            }
+fn read_poker_input(s: String) -> Result<Vec<PokerHand>, Box<dyn Error>> {
+    let ranks = Ruleset::standard().alphabet();
+    s.trim().lines().enumerate().map(|(n, line)| {
+        let line_no = n + 1;
+        let tokens = line.split_whitespace().collect_vec();
+        let Some((points, cards)) = tokens.split_last() else { Err(format!("line {}: empty line", line_no))? };
+        let points = points.parse().map_err(|e| format!("line {}: invalid bid '{}': {}", line_no, points, e))?;
+        if cards.len() != 5 {
+            Err(format!("line {}: expected 5 cards, found {}", line_no, cards.len()))?
         }
-    }
+        let cards = cards.iter().map(|card| match card.chars().collect_vec().as_slice() {
+            [rank, suit] if ranks.contains(rank) && "SHDC".contains(*suit) => Ok((*rank, *suit)),
+            _ => Err(format!("line {}: invalid card '{}'", line_no, card)),
+        }).collect::<Result<Vec<_>, _>>()?;
+        if let Some(card) = cards.iter().duplicates().next() {
+            Err(format!("line {}: card '{}{}' dealt twice", line_no, card.0, card.1))?
+        }
+        Ok(PokerHand { cards, points })
+    }).collect()
+}
 
-    fn cmp_first_card_with_jokers(left: &String, right: &String) -> Ordering {
-        let cmps = left.chars().zip(right.chars()).map(|(c1, c2)| Hand::card_value_with_jokers(c1).cmp(&Hand::card_value_with_jokers(c2)));
-        cmps.filter(|c| *c != Ordering::Equal).nth(0).unwrap()
-    }
+fn poker_winnings(hands: &[PokerHand]) -> Result<u64, String> {
+    Ok(total_winnings(rank_by(hands, |hand| hand.key().map(|key| (key, hand.points)))?.into_iter().map(|hand| hand.points)))
+}
 
-    fn card_value(card: char) -> u8 {
-        if card.is_numeric() {
-            u8::from_str_radix(&card.to_string(), 10).unwrap()
-        } else {
-            match card {
-                'A' => 14,
-                'K' => 13,
-                'Q' => 12,
-                'J' => 11,
-                'T' => 10,
-                _ => panic!(),
-            }
-        }
+type T = Vec<Hand>;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = std::env::args().collect_vec();
+    if let Some(path) = args.iter().skip_while(|arg| *arg != "--poker").nth(1) {
+        println!("{}", poker_winnings(&read_poker_input(fs::read_to_string(path)?)?)?);
+        return Ok(());
+    }
+    let input = read_input(fs::read_to_string("input/day7.txt")?, &[Ruleset::standard(), Ruleset::jokers()])?;
+    if args.iter().any(|arg| arg == "--explain") {
+        println!("part 1\n{}", explain(&input, &Ruleset::standard())?);
+        println!("part 2\n{}", explain(&input, &Ruleset::jokers())?);
+    }
+    if args.iter().any(|arg| arg == "--stats") {
+        println!("{}", report(&input)?);
     }
+    println!("{}", solution1(&input)?);
+    println!("{}", solution2(&input)?);
+    Ok(())
+}
 
-    fn typ_with_wildcard(&self, wildcard: char) -> HandType {
-        use HandType::*;
 
-        let jokers = self.cards.chars().filter(|c| *c == wildcard).count();
+// a nibble and a byte per card fill a u128 at ten cards
+const MAX_PACKED_LEN: usize = 10;
 
-        let mut lengths = self.cards.chars()
-            .filter(|c| *c != wildcard)
-            .sorted()
-            .group_by(|c| *c)
-            .into_iter()
-            .map(|(_c, g)| g.count())
-            .sorted()
-            .rev();
-
-        let mut binding = lengths.next();
-        let (fst, snd) = (binding.get_or_insert(0), lengths.next());
-
-        match (*fst + jokers, snd) {
-            (5, _) => FiveOfAKind,
-            (4, _) => FourOfAKind,
-            (3, Some(2)) => FullHouse,
-            (3, _) => ThreeOfAKind,
-            (2, Some(2)) => TwoPair,
-            (2, _) => OnePair,
-            _ => HighCard
-        }
-    }
+// KTJJT 220
+impl FromStr for Hand {
+    type Err = String;
 
-    fn typ(&self) -> HandType {
-        // never find this in a deck
-        self.typ_with_wildcard('*')
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((cards, points)) = s.split_whitespace().collect_tuple() else {
+            return Err(format!("expected cards and a bid, found '{}'", s))
+        };
+        let points = points.parse().map_err(|e| format!("invalid bid '{}': {}", points, e))?;
+        Ok(Hand { cards: cards.to_owned(), points })
     }
 }
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.typ() > other.typ() {
-            Ordering::Greater
-        } else if self.typ() < other.typ() {
-            Ordering::Less
-        } else {
-            Hand::cmp_first_card(&self.cards, &other.cards)
-        }        
+// every card has to be known to each of the rulesets the hands will be played under
+fn read_input(s: String, rulesets: &[Ruleset]) -> Result<T, Box<dyn Error>> {
+    let hands: T = s.trim_end().lines().enumerate().map(|(n, line)| {
+        let hand: Hand = line.parse().map_err(|e| format!("line {}: {}", n + 1, e))?;
+        rulesets.iter().try_for_each(|ruleset| ruleset.check(&hand)).map_err(|e| format!("line {}: {}", n + 1, e))?;
+        Ok(hand)
+    }).collect::<Result<_, String>>()?;
+
+    if let Some(first) = hands.first() {
+        let len = first.cards.chars().count();
+        if let Some((n, hand)) = hands.iter().find_position(|h| h.cards.chars().count() != len) {
+            Err(format!("line {}: hand has {} cards, but line 1 has {}", n + 1, hand.cards.chars().count(), len))?
+        }
     }
+    Ok(hands)
 }
 
-type T = Vec<Hand>;
+fn winnings(input: &T, ruleset: &Ruleset) -> Result<u64, String> {
+    Ok(total_winnings(ruleset.ranked(input)?.into_iter().map(|hand| hand.points)))
+}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let input = read_input(fs::read_to_string("input/day7.txt")?)?;
-    println!("{}", solution1(&input)?);
-    println!("{}", solution2(&input)?);
-    Ok(())
+// one line per hand, weakest first, with enough detail to check its rank by hand
+fn explain(input: &T, ruleset: &Ruleset) -> Result<String, String> {
+    let lines = ruleset.ranked(input)?.into_iter().enumerate().map(|(rank, hand)| {
+        let (resolved, typ) = ruleset.resolve(hand)?;
+        let rank = rank as u64 + 1;
+        Ok(format!("{:>5} {} -> {} {:<15} bid {:>4} wins {:>7}", rank, hand.cards, resolved, typ.to_string(), hand.points, rank * hand.points))
+    }).collect::<Result<Vec<_>, String>>()?;
+    Ok(lines.join("\n"))
 }
 
+/// Where one hand ended up under a ruleset. `marginal` is how much the total
+/// winnings would drop if the hand were left out: its own winnings, plus one
+/// bid from every stronger hand, which would each move down a rank.
+#[derive(Debug, PartialEq)]
+struct Standing {
+    rank: u64,
+    typ: HandType,
+    marginal: u64,
+}
 
-fn read_input(s: String) -> Result<T, Box<dyn Error>> {
-    s.trim().lines().map(|line| {
-        if let Some((cards, points)) = line.split_whitespace().next_tuple() {
-            Ok(Hand { cards: cards.to_owned(), points: u64::from_str_radix(points, 10).unwrap() })
-        } else {
-            Err("foo")?
-        }
-    }).collect()
+// in the same order as the input
+fn standings(input: &T, ruleset: &Ruleset) -> Result<Vec<Standing>, String> {
+    let indexed = input.iter().enumerate().collect_vec();
+    let ranked = rank_by(&indexed, |(_, hand)| ruleset.key(hand).map(|key| (key, hand.points)))?;
+
+    let mut standings = input.iter().map(|hand| Standing { rank: 0, typ: ruleset.typ(hand), marginal: 0 }).collect_vec();
+    let mut stronger_bids = 0;
+    for (rank, (i, hand)) in ranked.into_iter().enumerate().rev() {
+        let rank = rank as u64 + 1;
+        standings[*i].rank = rank;
+        standings[*i].marginal = rank * hand.points + stronger_bids;
+        stronger_bids += hand.points;
+    }
+    Ok(standings)
+}
+
+fn report(input: &T) -> Result<String, String> {
+    let part1 = standings(input, &Ruleset::standard())?;
+    let part2 = standings(input, &Ruleset::jokers())?;
+
+    let counts1 = part1.iter().map(|s| &s.typ).counts();
+    let counts2 = part2.iter().map(|s| &s.typ).counts();
+    let types = counts1.keys().chain(counts2.keys()).unique().sorted().rev().collect_vec();
+    let distribution = types.into_iter().map(|typ| format!(
+        "{:<15} {:>7} {:>7}", typ.to_string(), counts1.get(typ).unwrap_or(&0), counts2.get(typ).unwrap_or(&0)
+    ));
+
+    let changed = part1.iter().zip(part2.iter()).filter(|(s1, s2)| s1.typ != s2.typ).count();
+
+    let hands = input.iter().zip(part1.iter().zip(part2.iter())).map(|(hand, (s1, s2))| format!(
+        "{} {:>5} {:>7} {:>7} {:>+6} {:>10} {:>10}",
+        hand.cards, hand.points, s1.rank, s2.rank, s2.rank as i64 - s1.rank as i64, s1.marginal, s2.marginal
+    ));
+
+    Ok(itertools::chain!(
+        [format!("{:<15} {:>7} {:>7}", "hand type", "part 1", "part 2")],
+        distribution,
+        [String::new(), format!("{} hands change type with jokers", changed), String::new()],
+        [format!("{:<5} {:>5} {:>7} {:>7} {:>6} {:>10} {:>10}", "cards", "bid", "rank 1", "rank 2", "delta", "marginal 1", "marginal 2")],
+        hands
+    ).join("\n"))
 }
 
 fn solution1(input: &T) -> Result<u64, Box<dyn Error>> {
-    Ok(input.clone().into_iter().sorted_by(Hand::cmp).enumerate().map(|(rank, hand)| (rank+1) as u64 * hand.points).sum::<u64>())
+    Ok(winnings(input, &Ruleset::standard())?)
 }
 
 fn solution2(input: &T) -> Result<u64, Box<dyn Error>> {
-    println!("{:?}", (input.clone().into_iter().sorted_by(Hand::cmp_with_jokers).enumerate().collect_vec()));
-
-    Ok(input.clone().into_iter().sorted_by(Hand::cmp_with_jokers).enumerate().map(|(rank, hand)| (rank+1) as u64 * hand.points).sum::<u64>())
+    Ok(winnings(input, &Ruleset::jokers())?)
 }
 
 #[cfg(test)]
@@ -182,7 +456,7 @@ mod test {
         
     #[test]
     fn test_sample_ordering() -> Result<(), Box<dyn Error>> {
-        let mut hands = read_input(fs::read_to_string("sample/day7.txt")?)?;
+        let mut hands = read_input(fs::read_to_string("sample/day7.txt")?, &[Ruleset::standard(), Ruleset::jokers()])?;
         hands.sort_by(Hand::cmp);
 
         assert_eq!(hands[0].cards, "32T3K"); 
@@ -198,9 +472,230 @@ mod test {
     fn test_joker_sorting() -> Result<(), Box<dyn Error>> {
         let h1 = Hand { cards: "JJJJJ".to_owned(), points: 287 };
         let h2 = Hand { cards: "Q385K".to_owned(), points: 7 };
-        assert_eq!(Ordering::Greater, h1.cmp_with_jokers(&h2));
+        assert_eq!(Ordering::Greater, Ruleset::jokers().cmp(&h1, &h2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_ruleset() -> Result<(), Box<dyn Error>> {
+        let hands = read_input(fs::read_to_string("sample/day7.txt")?, &[Ruleset::standard(), Ruleset::jokers()])?;
+        assert_eq!(winnings(&hands, &Ruleset::standard())?, 6440);
+        assert_eq!(winnings(&hands, &Ruleset::jokers())?, 5905);
+
+        // both 2s and Js are wild, and wildcards outrank every other card in a tie
+        let ruleset = Ruleset::new("3456789TQKA", "2J", 15);
+        let h1 = Hand { cards: "2345J".to_owned(), points: 1 };
+        let h2 = Hand { cards: "AAA34".to_owned(), points: 1 };
+        assert_eq!(ruleset.typ(&h1), HandType(vec![3, 1, 1]));
+        assert_eq!(Ordering::Less, ruleset.cmp(&h2, &h1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_identical_cards() {
+        let h1 = Hand { cards: "KK677".to_owned(), points: 28 };
+        let h2 = Hand { cards: "KK677".to_owned(), points: 220 };
+        for ruleset in [Ruleset::standard(), Ruleset::jokers()] {
+            assert_eq!(Ordering::Equal, ruleset.cmp(&h1, &h2));
+            assert_eq!(Ordering::Less, ruleset.cmp_with_bids(&h1, &h2));
+        }
+        assert_eq!(Ordering::Less, h1.cmp(&h2));
+        assert_eq!(Some(Ordering::Equal), h1.partial_cmp(&h1.clone()));
+    }
+
+    // a small xorshift, so the property tests see the same hands on every run
+    fn random_hands(n: usize) -> Vec<Hand> {
+        let mut state: u64 = 0x2545F4914F6CDD1D;
+        let mut next = move || { state ^= state << 13; state ^= state >> 7; state ^= state << 17; state };
+        // a narrow alphabet makes equal types and shared prefixes common
+        let alphabet = ['2', '7', 'T', 'J', 'Q', 'A'];
+        (0..n).map(|_| Hand {
+            cards: (0..5).map(|_| alphabet[(next() % alphabet.len() as u64) as usize]).collect(),
+            points: next() % 3,
+        }).collect()
+    }
 
+    #[test]
+    fn test_ordering_properties() {
+        let hands = random_hands(40);
+        for ruleset in [Ruleset::standard(), Ruleset::jokers()] {
+            for (a, b) in hands.iter().cartesian_product(hands.iter()) {
+                assert_eq!(ruleset.cmp(a, b), ruleset.cmp(b, a).reverse(), "{:?} {:?}", a, b);
+                assert_eq!(ruleset.cmp_with_bids(a, b) == Ordering::Equal, a == b, "{:?} {:?}", a, b);
+                for c in hands.iter() {
+                    if ruleset.cmp(a, b) != Ordering::Greater && ruleset.cmp(b, c) != Ordering::Greater {
+                        assert_ne!(ruleset.cmp(a, c), Ordering::Greater, "{:?} {:?} {:?}", a, b, c);
+                    }
+                }
+            }
+        }
+        for (a, b) in hands.iter().cartesian_product(hands.iter()) {
+            assert_eq!(a.partial_cmp(b), Some(a.cmp(b)));
+            assert_eq!(a.cmp(b) == Ordering::Equal, a == b);
+        }
+    }
+
+    #[test]
+    fn test_key_matches_cmp() {
+        let hands = random_hands(200);
+        for ruleset in [Ruleset::standard(), Ruleset::jokers()] {
+            for (a, b) in hands.iter().cartesian_product(hands.iter()) {
+                assert_eq!(ruleset.key(a).unwrap().cmp(&ruleset.key(b).unwrap()), ruleset.cmp(a, b), "{:?} {:?}", a, b);
+            }
+            let ranked = ruleset.ranked(&hands).unwrap();
+            assert!(ranked.iter().tuple_windows().all(|(a, b)| ruleset.cmp_with_bids(a, b) != Ordering::Greater));
+        }
+    }
+
+    #[test]
+    fn test_resolve() -> Result<(), Box<dyn Error>> {
+        let jokers = Ruleset::jokers();
+        let resolve = |cards: &str| jokers.resolve(&Hand { cards: cards.to_owned(), points: 0 }).unwrap();
+        assert_eq!(resolve("T55J5"), ("T5555".to_owned(), HandType(vec![4, 1])));
+        assert_eq!(resolve("KTJJT"), ("KTTTT".to_owned(), HandType(vec![4, 1])));
+        assert_eq!(resolve("2345J"), ("23455".to_owned(), HandType(vec![2, 1, 1, 1])));
+        assert_eq!(resolve("JJJJJ"), ("AAAAA".to_owned(), HandType(vec![5])));
+        assert_eq!(resolve("32T3K"), ("32T3K".to_owned(), HandType(vec![2, 1, 1, 1])));
+        assert_eq!(Ruleset::standard().resolve(&Hand { cards: "T55J5".to_owned(), points: 0 })?.0, "T55J5");
+
+        for hand in random_hands(200) {
+            let (resolved, typ) = jokers.resolve(&hand)?;
+            assert_eq!(Ruleset::standard().typ(&Hand { cards: resolved, points: 0 }), typ);
+        }
+
+        let hands = read_input(fs::read_to_string("sample/day7.txt")?, &[Ruleset::standard(), Ruleset::jokers()])?;
+        let explanation = explain(&hands, &jokers)?;
+        assert_eq!(explanation.lines().last(), Some("    5 KTJJT -> KTTTT four of a kind  bid  220 wins    1100"));
         Ok(())
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_longer_hands() -> Result<(), Box<dyn Error>> {
+        let standard = Ruleset::standard();
+        let typ = |cards: &str| standard.typ(&Hand { cards: cards.to_owned(), points: 0 }).to_string();
+        assert_eq!(typ("KK77QQ"), "three pair");
+        assert_eq!(typ("KKK777"), "double triple");
+        assert_eq!(typ("KKK77Q"), "full house");
+        assert_eq!(typ("KKKK7Q"), "four of a kind");
+        assert_eq!(typ("KKKK77"), "4-2");
+        assert_eq!(typ("23456789"), "high card");
+
+        let hands = read_input("KK77QQ 1\nKKK777 2\n2345AA 3\nKKKK7Q 4\nJJJJJ2 5".to_owned(), &[Ruleset::standard()])?;
+        assert_eq!(ranked_cards(&hands, &standard), ["2345AA", "KK77QQ", "KKK777", "KKKK7Q", "JJJJJ2"]);
+        assert_eq!(ranked_cards(&hands, &Ruleset::jokers()), ["2345AA", "KK77QQ", "KKK777", "KKKK7Q", "JJJJJ2"]);
+
+        let err = read_input("KK77QQ 1\nKKK77 2".to_owned(), &[Ruleset::standard()]).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: hand has 5 cards, but line 1 has 6");
+
+        // past ten cards the keys no longer pack, and past ten of a kind the names run out
+        let long = read_input("KKKKKKKKKKK2 1\n22222222222K 2\n23456789TJQK 3".to_owned(), &[Ruleset::standard()])?;
+        assert!(matches!(standard.key(&long[0])?, Key::Unpacked(..)));
+        assert_eq!(ranked_cards(&long, &standard), ["23456789TJQK", "22222222222K", "KKKKKKKKKKK2"]);
+        assert_eq!(winnings(&long, &standard)?, 3 + 2 * 2 + 3);
+        let hands = random_hands(60).into_iter().tuples().map(|(a, b, c)| Hand { cards: a.cards + &b.cards + &c.cards, points: a.points }).collect_vec();
+        for ruleset in [Ruleset::standard(), Ruleset::jokers()] {
+            for (a, b) in hands.iter().cartesian_product(hands.iter()) {
+                assert_eq!(ruleset.key(a)?.cmp(&ruleset.key(b)?), ruleset.cmp(a, b), "{:?} {:?}", a, b);
+            }
+        }
+        assert_eq!(typ("KKKKKKKKKKK2"), "11-1");
+        assert_eq!(typ("223344556677889TTJJQQKKAA"), "2-2-2-2-2-2-2-2-2-2-2-2-1");
+        assert_eq!(typ("2233445566778899TTJJ"), "ten pair");
+        Ok(())
+    }
+
+    fn ranked_cards(hands: &T, ruleset: &Ruleset) -> Vec<String> {
+        ruleset.ranked(hands).unwrap().into_iter().map(|h| h.cards.clone()).collect()
+    }
+
+    #[test]
+    fn test_poker() -> Result<(), Box<dyn Error>> {
+        use PokerType::*;
+
+        let evaluate = |cards: &str| -> Result<(PokerType, Vec<u8>), Box<dyn Error>> {
+            Ok(read_poker_input(format!("{} 0", cards))?[0].evaluate()?)
+        };
+        assert_eq!(evaluate("TH JH QH KH AH")?, (StraightFlush, vec![14]));
+        assert_eq!(evaluate("AS 2D 3H 4C 5S")?, (Straight, vec![5]));
+        assert_eq!(evaluate("2D 9D 4D KD 7D")?, (Flush, vec![13, 9, 7, 4, 2]));
+        assert_eq!(evaluate("3S 3D KH KC 3H")?, (FullHouse, vec![3, 13]));
+        assert_eq!(evaluate("9S 9D 2H 2C AH")?, (TwoPair, vec![9, 2, 14]));
+        assert_eq!(evaluate("QS KD 2H 4C AH")?, (HighCard, vec![14, 13, 12, 4, 2]));
+
+        // the ace kicker wins, though the other pair of kings starts with the higher card
+        let hands = read_poker_input("2S KD KH AC 3S 10\nKS KC 9H 5C 2D 20\nAH 2D 3C 4S 5H 30".to_owned())?;
+        let ranked = rank_by(&hands, |hand| hand.key().map(|key| (key, hand.points)))?.into_iter().map(|h| h.points).collect_vec();
+        assert_eq!(ranked, [20, 10, 30]);
+        assert_eq!(poker_winnings(&hands)?, 20 + 2 * 10 + 3 * 30);
+
+        assert_eq!(read_poker_input("AS KD KH 4C 10".to_owned()).unwrap_err().to_string(), "line 1: expected 5 cards, found 4");
+        assert_eq!(read_poker_input("AS KD KH 4C 1X 10".to_owned()).unwrap_err().to_string(), "line 1: invalid card '1X'");
+        assert_eq!(read_poker_input("AS KD KH 4C AS 10".to_owned()).unwrap_err().to_string(), "line 1: card 'AS' dealt twice");
+        Ok(())
+    }
+
+    #[test]
+    fn test_standings() -> Result<(), Box<dyn Error>> {
+        let hands = read_input(fs::read_to_string("sample/day7.txt")?, &[Ruleset::standard(), Ruleset::jokers()])?;
+        for ruleset in [Ruleset::standard(), Ruleset::jokers()] {
+            let total = winnings(&hands, &ruleset)?;
+            for (i, standing) in standings(&hands, &ruleset)?.into_iter().enumerate() {
+                let mut without = hands.clone();
+                without.remove(i);
+                assert_eq!(standing.marginal, total - winnings(&without, &ruleset)?);
+            }
+        }
+
+        let part2 = standings(&hands, &Ruleset::jokers())?;
+        assert_eq!(part2[3], Standing { rank: 5, typ: HandType(vec![4, 1]), marginal: 1100 });
+
+        let report = report(&hands)?;
+        assert!(report.contains("3 hands change type with jokers"));
+        assert!(report.contains("four of a kind        0       3"));
+        assert!(report.contains("KTJJT   220       2       5     +3       1635       1100"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_hand() {
+        assert_eq!("KTJJT 220".parse(), Ok(Hand { cards: "KTJJT".to_owned(), points: 220 }));
+        assert_eq!("KTJJT".parse::<Hand>(), Err("expected cards and a bid, found 'KTJJT'".to_owned()));
+        assert_eq!("KTJJT 22O".parse::<Hand>(), Err("invalid bid '22O': invalid digit found in string".to_owned()));
+        assert_eq!("23456789TJQ 1".parse(), Ok(Hand { cards: "23456789TJQ".to_owned(), points: 1 }));
+        assert_eq!("♠♠♠♠♠ 1".parse(), Ok(Hand { cards: "♠♠♠♠♠".to_owned(), points: 1 }));
+
+        let err = read_input("32T3K 765\nT55J5 684\nKK6x7 28\n".to_owned(), &[Ruleset::standard()]).unwrap_err();
+        assert_eq!(err.to_string(), "line 3: invalid card 'x' at position 4");
+    }
+
+    #[test]
+    fn test_ruleset_alphabet() -> Result<(), Box<dyn Error>> {
+        // a variant with a card no other ruleset has
+        let stars = Ruleset::new("23456789TJQKA*", "", 0);
+        let hands = read_input("**234 1\nAAAA* 2".to_owned(), std::slice::from_ref(&stars))?;
+        assert_eq!(winnings(&hands, &stars)?, 1 + 2 * 2);
+        let err = read_input("**234 1".to_owned(), &[stars.clone(), Ruleset::standard()]).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: invalid card '*' at position 1");
+
+        // cards are counted as characters, however many bytes they take
+        let spades = Ruleset::new("23456789TJQKA♠", "", 0);
+        let hands = read_input("♠♠234 1\nAAAA♠ 2".to_owned(), std::slice::from_ref(&spades))?;
+        assert_eq!(winnings(&hands, &spades)?, 1 + 2 * 2);
+        let err = read_input("♠♠234 1\nAAAA 2".to_owned(), std::slice::from_ref(&spades)).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: hand has 4 cards, but line 1 has 5");
+
+        // wildcards outside the order still count as cards
+        let wild = Ruleset::new("3456789TQKA", "2J", 15);
+        assert_eq!(wild.alphabet().len(), 13);
+
+        // without aces, hands with them are turned away rather than valued
+        let no_aces = Ruleset::new("23456789TJQK", "", 0);
+        let err = read_input("AKQJT 1".to_owned(), std::slice::from_ref(&no_aces)).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: invalid card 'A' at position 1");
+        assert_eq!(no_aces.card_value('A'), Err("card 'A' is not part of this ruleset".to_owned()));
+        assert!(no_aces.key(&Hand { cards: "AKQJT".to_owned(), points: 1 }).is_err());
+        Ok(())
+    }
+}
@@ -1,18 +1,36 @@
-use std::collections::{HashSet, HashMap};
-use std::{error::Error, fs, cmp::Ordering};
-use std::fmt::Debug;
+use std::collections::HashMap;
+use std::{error::Error, fs};
 use std::fmt;
 use itertools::Itertools;
-use nom::multi::many1;
-use nom::{IResult, multi, InputIter};
-use nom::character::complete::{digit0, alpha1, space1, char, newline, alphanumeric1};
-use nom::combinator::map;
-use nom::sequence::{tuple, pair};
+use nom::IResult;
+use nom::character::complete::{space0, space1, char, alphanumeric1};
+use nom::combinator::{all_consuming, map};
+use nom::sequence::tuple;
 
 type Label = String;
 
 type T = (String, Vec<(Label, (Label, Label))>);
 
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    line: usize,
+    message: String,
+}
+
+impl ParseError {
+    fn new(line: usize, message: impl Into<String>) -> ParseError {
+        ParseError { line, message: message.into() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let input = read_input(fs::read_to_string("sample/day8.txt")?)?;
     // println!("{}", solution1(&input)?);
@@ -20,19 +38,56 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+// AAA = (BBB, CCC)
 fn parse_node(input: &str) -> IResult<&str, (Label, (Label, Label))> {
     map(
-        tuple((alphanumeric1::<&str, _>, space1, char('='), space1, char('('), alphanumeric1, char(','), space1, alphanumeric1, char(')'), newline)),
-        |(label, _, _, _, _, left, _, _, right, _, _)| (label.to_owned(), (left.to_owned(), right.to_owned()))
+        tuple((alphanumeric1::<&str, _>, space1, char('='), space1, char('('), alphanumeric1, char(','), space0, alphanumeric1, char(')'))),
+        |(label, _, _, _, _, left, _, _, right, _)| (label.to_owned(), (left.to_owned(), right.to_owned()))
     )(input)
 }
 
+fn read_instructions(line_no: usize, line: &str) -> Result<String, ParseError> {
+    if line.is_empty() {
+        return Err(ParseError::new(line_no, "missing instructions"));
+    }
+    match line.chars().find_position(|c| *c != 'L' && *c != 'R') {
+        Some((pos, c)) => Err(ParseError::new(line_no, format!("invalid instruction '{}' at position {}", c, pos + 1))),
+        None => Ok(line.to_owned()),
+    }
+}
+
 fn read_input(input: String) -> Result<T, Box<dyn Error>> {
-    let (_remainder, x) = map(
-        tuple((alpha1, newline, newline, many1(parse_node))),
-        |(instructions, _, _, labels)| (instructions.to_owned(), labels)
-    )(&input).map_err(|e| e.to_owned())?;
-    Ok(x)
+    // `lines` already strips the '\r' of a CRLF ending, and trimming the whole
+    // input first means a missing (or repeated) final newline doesn't matter
+    let mut lines = input.trim_end().lines().map(str::trim_end).enumerate().map(|(i, l)| (i + 1, l));
+
+    let instructions = read_instructions(1, lines.next().map(|(_, l)| l).unwrap_or(""))?;
+
+    match lines.next() {
+        Some((_, "")) => {},
+        Some((n, line)) => Err(ParseError::new(n, format!("expected a blank line, found `{}`", line)))?,
+        None => Err(ParseError::new(2, "missing node definitions"))?,
+    }
+
+    let mut seen: HashMap<Label, usize> = HashMap::new();
+    let mut nodes = vec![];
+    for (n, line) in lines {
+        let (_, node) = all_consuming(parse_node)(line).map_err(|e| {
+            let column = match e {
+                nom::Err::Error(e) | nom::Err::Failure(e) => line.len() - e.input.len() + 1,
+                nom::Err::Incomplete(_) => line.len() + 1,
+            };
+            ParseError::new(n, format!("unexpected input at column {}: `{}`", column, line))
+        })?;
+        if let Some(first) = seen.insert(node.0.clone(), n) {
+            Err(ParseError::new(n, format!("duplicate definition of node {} (first defined on line {})", node.0, first)))?
+        }
+        nodes.push(node);
+    }
+    if nodes.is_empty() {
+        Err(ParseError::new(3, "missing node definitions"))?
+    }
+    Ok((instructions, nodes))
 }
 
 fn solution1(input: &T) -> Result<u64, Box<dyn Error>> {
@@ -112,5 +167,23 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_read_input_tolerates_crlf_and_missing_newline() -> Result<(), Box<dyn Error>> {
+        let input = read_input("LLR \r\n\r\nAAA = (BBB, BBB)\r\nBBB = (AAA, ZZZ)  \r\nZZZ = (ZZZ, ZZZ)".to_owned())?;
+        assert_eq!(input.0, "LLR");
+        assert_eq!(input.1.len(), 3);
+        assert_eq!(input.1[2], ("ZZZ".to_owned(), ("ZZZ".to_owned(), "ZZZ".to_owned())));
+        assert_eq!(solution1(&input)?, 6);
+        Ok(())
+    }
 
+    #[test]
+    fn test_read_input_errors() {
+        let err = |s: &str| read_input(s.to_owned()).unwrap_err().to_string();
+        assert_eq!(err("LRX\n\nAAA = (AAA, AAA)\n"), "line 1: invalid instruction 'X' at position 3");
+        assert_eq!(err("LR\n\nAAA = (AAA, AAA)\nAAA = (BBB, BBB)\n"), "line 4: duplicate definition of node AAA (first defined on line 3)");
+        assert_eq!(err("LR\n\nAAA = (AAA, AAA)\nAAA = (BBB, BBB) junk\n"), "line 4: unexpected input at column 17: `AAA = (BBB, BBB) junk`");
+        assert_eq!(err("LR\nAAA = (AAA, AAA)\n"), "line 2: expected a blank line, found `AAA = (AAA, AAA)`");
+        assert_eq!(err("LR\n"), "line 2: missing node definitions");
+    }
 }
\ No newline at end of file